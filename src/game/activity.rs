@@ -0,0 +1,74 @@
+use std::{collections::VecDeque, mem};
+
+/// How many ticks the activity is counted over.
+pub const ACTIVITY_WINDOW: usize = 100;
+
+/// Counts how often every position of a grid changed during the last ticks.
+#[derive(Debug, Clone)]
+pub struct Activity {
+    /// Amount of the last ticks each position changed in, indexed like the cells of the grid.
+    pub counts: Vec<u16>,
+    /// Positions changed during each of the last ticks, oldest first.
+    history: VecDeque<Vec<usize>>,
+    window: usize,
+    /// Positions changed during the current tick, possibly more than once.
+    current: Vec<usize>,
+}
+
+impl Activity {
+    pub fn new(window: usize) -> Self {
+        Activity {
+            counts: Vec::new(),
+            history: VecDeque::with_capacity(window + 1),
+            window,
+            current: Vec::new(),
+        }
+    }
+
+    /// Starts counting a tick of a grid with the amount of positions.
+    pub fn begin(&mut self, len: usize) {
+        if self.counts.len() != len {
+            // the grid was resized
+            self.counts = vec![0; len];
+            self.history.clear();
+        }
+        self.current.clear();
+    }
+
+    /// Records that a position changed during the current tick.
+    #[inline(always)]
+    pub fn touch(&mut self, index: usize) {
+        self.current.push(index);
+    }
+
+    /// Counts the positions changed since `begin`.
+    pub fn end(&mut self) {
+        let mut changed = mem::take(&mut self.current);
+        changed.sort_unstable();
+        changed.dedup();
+        changed.retain(|&i| i < self.counts.len());
+        for &i in &changed {
+            self.counts[i] += 1;
+        }
+        self.history.push_back(changed);
+
+        if self.history.len() > self.window {
+            for i in self.history.pop_front().unwrap() {
+                self.counts[i] -= 1;
+            }
+        }
+    }
+
+    /// Amount of ticks counted so far, at most the window.
+    pub fn ticks(&self) -> usize {
+        self.history.len()
+    }
+
+    /// Share of the counted ticks the position changed in, from 0 to 1.
+    pub fn heat(&self, index: usize) -> f32 {
+        match self.counts.get(index) {
+            Some(&count) if count > 0 => count as f32 / self.ticks() as f32,
+            _ => 0.0,
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use std::fmt::Debug;
+
+use super::{cells::Cell, direction::Direction, manipulation::MoveForce, update::{Subtick, MIRRORS, CROSSMIRRORS, SUCKERS, ORIENTATORS, TRASHPULLERS, TRASHMOVERS}};
+
+/// Defines how a cell type interacts with other cells.
+///
+/// Every method defaults to the behavior of a push cell,
+/// so implementations only have to override what makes their cell special.
+pub trait CellBehavior: Sync {
+    /// Checks if the cell can move in a certain direction with the given force.
+    fn can_move(&self, _cell: &Cell, _direction: Direction, _force: MoveForce) -> bool { true }
+
+    /// Checks if the cell is a trash for cells moving into it in the direction.
+    fn is_trash(&self, _cell: &Cell, _direction: Direction) -> bool { false }
+
+    /// Checks if the cell is an enemy, which is destroyed together with the cell moved into it.
+    fn is_enemy(&self, _cell: &Cell) -> bool { false }
+
+    /// Checks if the cell can be rotated from a specific side.
+    fn can_rotate(&self, _cell: &Cell, _side: Direction) -> bool { true }
+
+    /// Checks if the cell can be generated.
+    fn can_generate(&self, _cell: &Cell) -> bool { true }
+
+    /// The force the cell adds to a push or pull moving it in the direction.
+    fn force(&self, _cell: &Cell, _direction: Direction) -> isize { 0 }
+
+    /// The subtick updating this cell type.
+    fn subtick(&self) -> Option<&'static Subtick> { None }
+}
+
+impl Debug for dyn CellBehavior {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "CellBehavior")
+    }
+}
+
+/// A cell that does nothing on its own.
+pub struct Passive;
+impl CellBehavior for Passive {}
+
+/// A cell that is updated by a subtick but otherwise acts like a push cell.
+pub struct Updated(pub &'static Subtick);
+impl CellBehavior for Updated {
+    fn subtick(&self) -> Option<&'static Subtick> { Some(self.0) }
+}
+
+/// A cell that adds force to pushes and pulls along its direction.
+pub struct Forcing(pub Option<&'static Subtick>);
+impl CellBehavior for Forcing {
+    fn force(&self, cell: &Cell, direction: Direction) -> isize { forward_force(cell, direction) }
+    fn subtick(&self) -> Option<&'static Subtick> { self.0 }
+}
+
+pub struct Wall;
+impl CellBehavior for Wall {
+    fn can_move(&self, _: &Cell, _: Direction, _: MoveForce) -> bool { false }
+    fn can_rotate(&self, _: &Cell, _: Direction) -> bool { false }
+}
+
+pub struct Ghost;
+impl CellBehavior for Ghost {
+    fn can_move(&self, _: &Cell, _: Direction, _: MoveForce) -> bool { false }
+    fn can_rotate(&self, _: &Cell, _: Direction) -> bool { false }
+    fn can_generate(&self, _: &Cell) -> bool { false }
+}
+
+pub struct Slide;
+impl CellBehavior for Slide {
+    fn can_move(&self, cell: &Cell, direction: Direction, _: MoveForce) -> bool {
+        cell.direction() % 2 == direction % 2
+    }
+}
+
+pub struct SlideWall;
+impl CellBehavior for SlideWall {
+    fn can_move(&self, cell: &Cell, direction: Direction, _: MoveForce) -> bool {
+        cell.direction() % 2 == direction % 2
+    }
+    fn can_rotate(&self, cell: &Cell, side: Direction) -> bool {
+        (cell.direction() - side).shrink(2) != Direction::Down
+    }
+}
+
+pub struct OneDir;
+impl CellBehavior for OneDir {
+    fn can_move(&self, cell: &Cell, direction: Direction, _: MoveForce) -> bool {
+        cell.direction() == direction
+    }
+}
+
+pub struct Mirror;
+impl CellBehavior for Mirror {
+    fn can_move(&self, cell: &Cell, direction: Direction, force: MoveForce) -> bool {
+        !(force == MoveForce::Swap && cell.direction() % 2 == direction % 2)
+    }
+    fn subtick(&self) -> Option<&'static Subtick> { Some(&MIRRORS) }
+}
+
+pub struct CrossMirror;
+impl CellBehavior for CrossMirror {
+    fn can_move(&self, _: &Cell, _: Direction, force: MoveForce) -> bool {
+        force != MoveForce::Swap
+    }
+    fn subtick(&self) -> Option<&'static Subtick> { Some(&CROSSMIRRORS) }
+}
+
+pub struct Trash;
+impl CellBehavior for Trash {
+    fn is_trash(&self, _: &Cell, _: Direction) -> bool { true }
+}
+
+pub struct Enemy;
+impl CellBehavior for Enemy {
+    fn is_trash(&self, _: &Cell, _: Direction) -> bool { true }
+    fn is_enemy(&self, _: &Cell) -> bool { true }
+}
+
+pub struct TrashMover;
+impl CellBehavior for TrashMover {
+    fn is_trash(&self, cell: &Cell, direction: Direction) -> bool { cell.direction() == direction.flip() }
+    fn force(&self, cell: &Cell, direction: Direction) -> isize { forward_force(cell, direction) }
+    fn subtick(&self) -> Option<&'static Subtick> { Some(&TRASHMOVERS) }
+}
+
+pub struct TrashPuller;
+impl CellBehavior for TrashPuller {
+    fn is_trash(&self, cell: &Cell, direction: Direction) -> bool { cell.direction() == direction }
+    fn subtick(&self) -> Option<&'static Subtick> { Some(&TRASHPULLERS) }
+}
+
+pub struct Sucker;
+impl CellBehavior for Sucker {
+    fn is_trash(&self, cell: &Cell, direction: Direction) -> bool { cell.direction() == direction.flip() }
+    fn subtick(&self) -> Option<&'static Subtick> { Some(&SUCKERS) }
+}
+
+pub struct Orientator;
+impl CellBehavior for Orientator {
+    fn can_rotate(&self, _: &Cell, _: Direction) -> bool { false }
+    fn subtick(&self) -> Option<&'static Subtick> { Some(&ORIENTATORS) }
+}
+
+/// Force of a cell pushing or pulling in its own direction.
+#[inline(always)]
+pub fn forward_force(cell: &Cell, direction: Direction) -> isize {
+    if cell.direction() == direction { 1 }
+    else if cell.direction() == direction.flip() { -1 }
+    else { 0 }
+}
@@ -0,0 +1,144 @@
+use std::{fs, path::PathBuf};
+
+use image::RgbaImage;
+
+use super::{cells::Grid, codes::{import, export_q2}, direction::Direction, settings::data_dir};
+
+/// A saved machine that can be placed as a stamp.
+#[derive(Debug, Clone)]
+pub struct Blueprint {
+    pub name: String,
+    pub grid: Grid,
+    /// Thumbnail saved next to the blueprint, if there is one.
+    pub thumbnail: Option<RgbaImage>,
+    /// File name without the extension.
+    file_name: String,
+}
+
+/// The blueprints in the `blueprints` folder of the data directory.
+///
+/// Every blueprint is a `.txt` file with the name on the first line and a level code on the second,
+/// and a `.png` thumbnail with the same file name.
+#[derive(Debug, Clone)]
+pub struct BlueprintLibrary {
+    pub blueprints: Vec<Blueprint>,
+    /// Not set if there is no data directory, blueprints can't be saved then.
+    folder: Option<PathBuf>,
+}
+
+impl BlueprintLibrary {
+    /// Loads all blueprints in the folder. Blueprints that can't be imported are skipped.
+    pub fn load() -> Self {
+        let folder = data_dir().map(|dir| dir.join("blueprints"));
+
+        let mut paths = folder.as_ref()
+            .and_then(|folder| fs::read_dir(folder).ok())
+            .map(|entries| entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+                .collect::<Vec<_>>())
+            .unwrap_or_default();
+        paths.sort();
+
+        let mut blueprints = Vec::new();
+        for path in paths {
+            match fs::read_to_string(&path).map_err(|e| e.to_string()).and_then(|text| parse_blueprint(&text)) {
+                Ok((name, grid)) => blueprints.push(Blueprint {
+                    name,
+                    grid,
+                    thumbnail: image::open(path.with_extension("png")).ok().map(|image| image.to_rgba8()),
+                    file_name: path.file_stem().unwrap().to_string_lossy().into_owned(),
+                }),
+                Err(err) => eprintln!("{}: {err}", path.display()),
+            }
+        }
+
+        BlueprintLibrary { blueprints, folder }
+    }
+
+    /// Indices of the blueprints whose name contains the query, ignoring case.
+    pub fn search(&self, query: &str) -> Vec<usize> {
+        let query = query.to_lowercase();
+        (0..self.blueprints.len())
+            .filter(|&i| self.blueprints[i].name.to_lowercase().contains(&query))
+            .collect()
+    }
+
+    /// Saves a blueprint to disk, replacing a blueprint with the same name.
+    /// Returns the index of the blueprint.
+    pub fn save(&mut self, name: String, grid: Grid, thumbnail: RgbaImage) -> Result<usize, String> {
+        let folder = self.folder.as_ref().ok_or("no data directory to save blueprints in")?;
+        let file_name = match self.blueprints.iter().find(|b| b.name == name) {
+            Some(blueprint) => blueprint.file_name.clone(),
+            None => {
+                // different names can have the same file name, the later ones get a number
+                let base = file_name(&name);
+                let mut file_name = base.clone();
+                let mut number = 1;
+                while self.blueprints.iter().any(|b| b.file_name == file_name) || folder.join(&file_name).with_extension("txt").exists() {
+                    number += 1;
+                    file_name = format!("{base}-{number}");
+                }
+                file_name
+            },
+        };
+
+        let path = folder.join(&file_name);
+        fs::create_dir_all(folder).map_err(|e| e.to_string())?;
+        fs::write(path.with_extension("txt"), format!("{name}\n{}", export_q2(&grid))).map_err(|e| e.to_string())?;
+        thumbnail.save(path.with_extension("png")).map_err(|e| e.to_string())?;
+
+        let blueprint = Blueprint { name, grid, thumbnail: Some(thumbnail), file_name };
+        match self.blueprints.iter().position(|b| b.name == blueprint.name) {
+            Some(index) => {
+                self.blueprints[index] = blueprint;
+                Ok(index)
+            },
+            None => {
+                self.blueprints.push(blueprint);
+                Ok(self.blueprints.len() - 1)
+            },
+        }
+    }
+}
+
+fn parse_blueprint(text: &str) -> Result<(String, Grid), String> {
+    let (name, code) = text.split_once('\n').ok_or("missing level code")?;
+    let grid = import(code.trim()).map_err(str::to_string)?;
+    Ok((name.trim().to_string(), grid))
+}
+
+/// File name of a blueprint without the extension.
+fn file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c.to_ascii_lowercase() } else { '_' })
+        .collect()
+}
+
+/// Copies the cells between two corners into a new grid.
+pub fn copy_region(source: &Grid, min: (isize, isize), max: (isize, isize)) -> Grid {
+    let mut region = Grid::new((max.0 - min.0 + 1) as usize, (max.1 - min.1 + 1) as usize);
+    for y in min.1..=max.1 {
+        for x in min.0..=max.0 {
+            region.set_cell(x - min.0, y - min.1, source.get(x, y).clone());
+        }
+    }
+    region
+}
+
+/// Rotates a grid clockwise, so that cells facing right face the direction afterwards.
+pub fn rotate_grid(source: &Grid, direction: Direction) -> Grid {
+    let mut rotated = source.clone();
+    for _ in 0..u8::from(direction) {
+        let mut next = Grid::new(rotated.height, rotated.width);
+        rotated.for_each(|x, y, cell| {
+            if let Some(cell) = cell {
+                let mut cell = cell.clone();
+                cell.set_direction(cell.direction() + 1);
+                next.set(y, rotated.width as isize - 1 - x, cell);
+            }
+        });
+        rotated = next;
+    }
+    rotated
+}
@@ -1,4 +1,6 @@
-use crate::game::cells::CellType;
+use std::sync::OnceLock;
+
+use crate::game::{cells::CellType, behavior::{CellBehavior, Passive, Updated, Forcing, Wall, Ghost, Slide, SlideWall, OneDir, Mirror, CrossMirror, Trash, Enemy, TrashMover, TrashPuller, Sucker, Orientator}, update::{TUNNELS, FIXED_PULLSHERS, GENS, ANGLED_GENS, PHYSICAL_GENS, CROSS_GENS, REPLICATORS, ROTATORS, STONES, PULLSHERS, PULLERS, MOVERS, SPEEDS, PLAYERS}};
 
 // helper for easier cell definitions
 macro_rules! celld {
@@ -7,7 +9,8 @@ macro_rules! celld {
             $name:literal,
             $description:literal,
             sides $sides:literal,
-            texture $texture_name:literal
+            texture $texture_name:literal,
+            behavior $behavior:expr
         }
     )*} => {
         $( pub const $id_name: CellType = $id; )*
@@ -19,6 +22,7 @@ macro_rules! celld {
                     description: $description,
                     sides: $sides,
                     texture_name: $texture_name,
+                    behavior: $behavior,
                 },
             )*
         ];
@@ -38,163 +42,190 @@ celld! {
         "Wall",
         "A solid wall that can't be moved by anything.",
         sides 1,
-        texture "wall"
+        texture "wall",
+        behavior &Wall
     }
     MOVER 2 {
         "Mover",
         "Pushes the cells in front of it.",
         sides 4,
-        texture "mover"
+        texture "mover",
+        behavior &Forcing(Some(&MOVERS))
     }
     PULLER 3 {
         "Puller",
         "Pulls the cells behind it.",
         sides 4,
-        texture "puller"
+        texture "puller",
+        behavior &Forcing(Some(&PULLERS))
     }
     PULLSHER 4 {
         "Pullsher",
         "Pulls the cells behind it and pushes the cells in front of it.",
         sides 4,
-        texture "pullsher"
+        texture "pullsher",
+        behavior &Forcing(Some(&PULLSHERS))
     }
     GENERATOR 5 {
         "Generator",
         "Generates the cell behind to its front.",
         sides 4,
-        texture "generator"
+        texture "generator",
+        behavior &Updated(&GENS)
     }
     ROTATOR_CW 6 {
         "Rotator CW",
         "Rotates all touching cells clockwise.",
         sides 1,
-        texture "rotator_cw"
+        texture "rotator_cw",
+        behavior &Updated(&ROTATORS)
     }
     ROTATOR_CCW 7 {
         "Rotator CCW",
         "Rotates all touching cells counter-clockwise.",
         sides 1,
-        texture "rotator_ccw"
+        texture "rotator_ccw",
+        behavior &Updated(&ROTATORS)
     }
     ORIENTATOR 8 {
         "Orientator",
         "Rotates all touching cells in its own direction.",
         sides 4,
-        texture "orientator"
+        texture "orientator",
+        behavior &Orientator
     }
     PUSH 9 {
         "Push",
         "A normal cell that does nothing.",
         sides 1,
-        texture "push"
+        texture "push",
+        behavior &Passive
     }
     SLIDE 10 {
         "Slide",
         "Like push cell but can only be moved in two directions.",
         sides 2,
-        texture "slide"
+        texture "slide",
+        behavior &Slide
     }
     TRASH 11 {
         "Trash",
         "Trashes all cells that get moved into it.",
         sides 1,
-        texture "trash"
+        texture "trash",
+        behavior &Trash
     }
     ENEMY 12 {
         "Enemy",
         "An enemy that moves randomly. *thanks github copilot*",
         sides 1,
-        texture "enemy"
+        texture "enemy",
+        behavior &Enemy
     }
     MIRROR 13 {
         "Mirror",
         "Flips the cell in front and behind.",
         sides 2,
-        texture "mirror"
+        texture "mirror",
+        behavior &Mirror
     }
     CROSSMIRROR 14 {
         "Cross-Mirror",
         "Like mirror but stacked 90 degrees.",
         sides 1,
-        texture "crossmirror"
+        texture "crossmirror",
+        behavior &CrossMirror
     }
     TRASHMOVER 15 {
         "Trash Mover",
         "Like a mover but deletes all cells in front of it.",
         sides 4,
-        texture "trashmover"
+        texture "trashmover",
+        behavior &TrashMover
     }
     SPEED 16 {
         "Speed",
         "Fast mover but can't push.",
         sides 4,
-        texture "speed"
+        texture "speed",
+        behavior &Forcing(Some(&SPEEDS))
     }
     MOVLER 17 {
         "Movler",
         "Very weird combination of mover and puller. Doesn't push or pull but increases force when being pushed or pulled.",
         sides 4,
-        texture "movler"
+        texture "movler",
+        behavior &Forcing(None)
     }
     ONE_DIR 18 {
         "One Dir",
         "A cell that can only be moved in one direction.",
         sides 4,
-        texture "one_dir"
+        texture "one_dir",
+        behavior &OneDir
     }
     SLIDE_WALL 19 {
         "Slide Wall",
         "Like slide but can't be rotated from unmovable sides.",
         sides 2,
-        texture "slide_wall"
+        texture "slide_wall",
+        behavior &SlideWall
     }
     GENERATOR_CW 20 {
         "Generator CW",
         "Like generator but rotates to it's right side.",
         sides 4,
-        texture "generator_cw"
+        texture "generator_cw",
+        behavior &Updated(&ANGLED_GENS)
     }
     GENERATOR_CCW 21 {
         "Generator CCW",
         "Like generator but rotates to it's left side.",
         sides 4,
-        texture "generator_ccw"
+        texture "generator_ccw",
+        behavior &Updated(&ANGLED_GENS)
     }
     TRASHPULLER 22 {
         "Trash Puller",
         "Tries to trash the cell behind it, then moves.",
         sides 4,
-        texture "trashpuller"
+        texture "trashpuller",
+        behavior &TrashPuller
     }
     GHOST 23 {
         "Ghost",
         "A wall that can't be generated.",
         sides 1,
-        texture "ghost"
+        texture "ghost",
+        behavior &Ghost
     }
     STONE 24 {
         "Stone",
         "A cell with gravity. Will try to form hills.",
         sides 4,
-        texture "stone"
+        texture "stone",
+        behavior &Updated(&STONES)
     }
     REPLICATOR 25 {
         "Replicator",
         "A generator that generates the cell in front of it.",
         sides 4,
-        texture "replicator"
+        texture "replicator",
+        behavior &Updated(&REPLICATORS)
     }
     SUCKER 26 {
         "Sucker",
         "Trashes the cell in front of it.",
         sides 4,
-        texture "sucker"
+        texture "sucker",
+        behavior &Sucker
     }
     GENERATOR_CROSS 27 {
         "Generator Cross",
         "Like a generator but stacked 90 degrees.",
         sides 4,
-        texture "generator_cross"
+        texture "generator_cross",
+        behavior &Updated(&CROSS_GENS)
     }
     // MAILBOX 28 {
     //     "Mailbox",
@@ -212,45 +243,112 @@ celld! {
         "Physical Generator",
         "Generates the cell in front of it. If it hits a wall it pushes itself back.",
         sides 4,
-        texture "physical_generator"
+        texture "physical_generator",
+        behavior &Updated(&PHYSICAL_GENS)
     }
     ROTATOR_180 29 {
         "Rotator 180",
         "Rotates all touching cells 180 degrees.",
         sides 1,
-        texture "rotator_180"
+        texture "rotator_180",
+        behavior &Updated(&ROTATORS)
     }
     TUNNEL 30 {
         "Tunnel",
         "Tries to push the cell behind to it's front.",
         sides 4,
-        texture "tunnel"
+        texture "tunnel",
+        behavior &Updated(&TUNNELS)
     }
     FIXED_PULLSHER 31 {
         "Fixed Pullsher",
         "Like tunnel, but pulls from behind.",
         sides 4,
-        texture "fixed_pullsher"
+        texture "fixed_pullsher",
+        behavior &Updated(&FIXED_PULLSHERS)
+    }
+    REDIRECTOR 32 {
+        "Redirector",
+        "Sets the direction of cells moved next to it to its own direction.",
+        sides 4,
+        texture "redirector",
+        behavior &Passive
+    }
+    PLAYER 33 {
+        "Player",
+        "Moves in the direction of the arrow keys while the simulation is running.",
+        sides 4,
+        texture "player",
+        behavior &Updated(&PLAYERS)
     }
-    // IMPORTANT: DO NOT ADD MORE CELLS HERE
 }
 
 hotbar![
     [WALL, GHOST, STONE],
+    [PLAYER],
     [MOVER, PULLER, PULLSHER, MOVLER, TRASHMOVER, TRASHPULLER, SPEED],
     [GENERATOR, GENERATOR_CW, GENERATOR_CCW, GENERATOR_CROSS, REPLICATOR, PHYSICAL_GENERATOR],
-    [ROTATOR_CW, ROTATOR_CCW, ROTATOR_180, ORIENTATOR],
+    [ROTATOR_CW, ROTATOR_CCW, ROTATOR_180, ORIENTATOR, REDIRECTOR],
     [PUSH, SLIDE, ONE_DIR, SLIDE_WALL],
     [TRASH, ENEMY, SUCKER],
     // [MAILBOX, POSTOFFICE],
     [MIRROR, CROSSMIRROR, TUNNEL, FIXED_PULLSHER],
 ];
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy)]
 pub struct CellData {
     pub id: CellType,
     pub name: &'static str,
     pub description: &'static str,
     pub sides: usize,
     pub texture_name: &'static str,
+    pub behavior: &'static dyn CellBehavior,
+}
+
+/// Cell types registered at runtime, see `register_cells`.
+static REGISTERED_CELLS: OnceLock<Vec<CellData>> = OnceLock::new();
+
+/// Registers the cell types added at runtime and returns them with their ids set.
+/// The `id`s of the passed data are ignored.
+///
+/// Can only be called once, before any grid containing the cells is updated or drawn.
+pub fn register_cells(mut cells: Vec<CellData>) -> &'static [CellData] {
+    assert!(CELL_DATA.len() + cells.len() <= CellType::MAX as usize, "too many cell types");
+    for (i, data) in cells.iter_mut().enumerate() {
+        data.id = (CELL_DATA.len() + i + 1) as CellType;
+    }
+    assert!(REGISTERED_CELLS.set(cells).is_ok(), "cells can only be registered once");
+    registered_cells()
+}
+
+/// The cell types registered at runtime.
+#[inline(always)]
+fn registered_cells() -> &'static [CellData] {
+    REGISTERED_CELLS.get().map_or(&[], Vec::as_slice)
+}
+
+/// Gets the data of a cell type, including registered ones.
+#[inline(always)]
+pub fn cell_data(id: CellType) -> &'static CellData {
+    let index = id as usize - 1;
+    if index < CELL_DATA.len() {
+        &CELL_DATA[index]
+    }
+    else {
+        &registered_cells()[index - CELL_DATA.len()]
+    }
+}
+
+/// Iterates over all cell types, including registered ones.
+pub fn all_cells() -> impl Iterator<Item = &'static CellData> {
+    CELL_DATA.iter().chain(registered_cells())
+}
+
+/// The hotbar groups, followed by a group of all registered cells if there are any.
+pub fn hotbar_items() -> Vec<&'static [CellData]> {
+    let mut items = HOTBAR_ITEMS.to_vec();
+    if !registered_cells().is_empty() {
+        items.push(registered_cells());
+    }
+    items
 }
@@ -1,9 +1,13 @@
-use std::mem;
+use std::{mem, collections::VecDeque, num::NonZeroU16};
 
-use super::{direction::Direction, cell_data::CELL_DATA};
+use super::{direction::Direction, cell_data::cell_data, events::{UpdateEvent, CellMotion}, update::{SubtickProfile, UpdateOrder}, puzzle::Puzzle, activity::{Activity, ACTIVITY_WINDOW}};
 
 pub const DEFAULT_GRID_WIDTH: usize = 100;
 pub const DEFAULT_GRID_HEIGHT: usize = 100;
+/// Largest width and height of a grid loaded from a level code or the settings.
+pub const MAX_GRID_SIZE: usize = 10_000;
+/// How many player inputs can be waiting at once, further inputs are dropped.
+pub const MAX_QUEUED_INPUTS: usize = 8;
 
 pub type CellType = u8;
 
@@ -13,76 +17,101 @@ static mut DUMMY_CELL: Option<Cell> = None;
 ///
 /// # Internal layout
 ///
-/// The cell is stored as a single byte. It uses the following layout:
+/// The cell is stored in two bytes. It uses the following layout:
 /// ```txt
-/// UCCCCCDD
+/// U-----CCCCCCCCDD
 /// ```
 /// Where:
 /// - `U`: The updated flag. If set, the cell has been updated this tick.
+/// - `-`: Unused.
 /// - `C`: The cell type.
 /// - `D`: The direction.
+///
+/// Cell types start at 1, so the value is never zero and `Option<Cell>` takes two bytes as well.
 #[derive(Debug)]
-pub struct Cell(u8);
+pub struct Cell(NonZeroU16);
 
 impl Cell {
     /// Creates a new cell.
+    /// Panics if the cell type is 0.
     #[inline(always)]
     pub fn new(id: CellType, direction: Direction) -> Self {
-        Cell((id << 2) | direction as u8)
+        Cell(NonZeroU16::new(((id as u16) << 2) | direction as u16).expect("cell type 0"))
+    }
+
+    /// Replaces the bits outside of the cell type.
+    #[inline(always)]
+    fn set_bits(&mut self, bits: u16) {
+        // SAFETY: the cell type bits are kept and the cell type is never zero
+        self.0 = unsafe { NonZeroU16::new_unchecked((self.0.get() & 0x3fc) | (bits & !0x3fc)) };
     }
 
     /// Gets the cell type.
     #[inline(always)]
     pub fn id(&self) -> CellType {
-        (self.0 & 124) >> 2
+        (self.0.get() >> 2) as CellType
     }
 
     /// Gets the direction.
     #[inline(always)]
     pub fn direction(&self) -> Direction {
-        (self.0 & 3).into()
+        (self.0.get() as u8 & 3).into()
     }
 
     /// Sets the direction.
     #[inline(always)]
     pub fn set_direction(&mut self, direction: Direction) {
-        self.0 = (self.0 & 252) | (direction as u8);
+        self.set_bits((self.0.get() & !3) | (direction as u16));
     }
 
     /// Gets the updated flag.
     #[inline(always)]
     pub fn updated(&self) -> bool {
-        self.0 & 128 != 0
+        self.0.get() & 0x8000 != 0
     }
 
     /// Sets the updated flag.
     #[inline(always)]
     pub fn set_updated(&mut self, updated: bool) {
-        self.0 = (self.0 & 127) | ((updated as u8) << 7);
+        self.set_bits((self.0.get() & 0x7fff) | ((updated as u16) << 15));
     }
 
     pub fn looks_like(&self, other: &Cell) -> bool {
-        if self.0 & 124 != other.0 & 124 {
+        if self.id() != other.id() {
             return false;
         }
-        let max_rot = CELL_DATA.iter().find(|cd| cd.id == (self.0 & 124) >> 2).unwrap().sides as u8;
-        (self.0 & 3) % max_rot == (other.0 & 3) % max_rot
+        let max_rot = cell_data(self.id()).sides as u8;
+        self.direction() % max_rot == other.direction() % max_rot
     }
 }
 
 impl Clone for Cell {
     fn clone(&self) -> Self {
-        Cell(self.0 & 127)
+        let mut cell = Cell(self.0);
+        cell.set_updated(false);
+        cell
     }
 }
 
 impl PartialEq for Cell {
     fn eq(&self, other: &Cell) -> bool {
-        self.0 & 127 == other.0 & 127
+        self.0.get() & 0x7fff == other.0.get() & 0x7fff
     }
 }
 impl Eq for Cell {}
 
+/// Amount of camera bookmarks a grid can have.
+pub const BOOKMARK_COUNT: usize = 9;
+
+/// A saved camera position, see `Grid::bookmarks`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Bookmark {
+    /// Grid position at the center of the screen.
+    pub x: f32,
+    pub y: f32,
+    pub zoom: f32,
+}
+
 /// A whole grid of cells.
 #[derive(Debug, Clone)]
 pub struct Grid {
@@ -90,6 +119,27 @@ pub struct Grid {
     pub height: usize,
     cells: Vec<Option<Cell>>,
     pub tick_count: u32,
+    /// The order cells are updated in.
+    pub update_order: UpdateOrder,
+    /// Events recorded during updates. Set to `Some` to start recording.
+    pub events: Option<Vec<UpdateEvent>>,
+    /// Subtick timings of the last update. Set to `Some` to start profiling.
+    pub profile: Option<Vec<SubtickProfile>>,
+    /// Motions of the cells during the last update, indexed like the cells. Set to `Some` to start recording.
+    pub motion: Option<Vec<CellMotion>>,
+    /// How often the positions changed during the last updates. Set to `Some` to start counting.
+    pub activity: Option<Activity>,
+    /// Copies of the grid after every update, for recordings on the update thread. Set to `Some` to start capturing.
+    pub frames: Option<Vec<Grid>>,
+    /// Whether there might be redirectors on the grid.
+    /// Moved cells are only checked for redirectors next to them if this is set.
+    pub has_redirectors: bool,
+    /// Player inputs waiting to be processed, one per tick.
+    pub input: VecDeque<Direction>,
+    /// Build zone, inventory and goal if the level is a puzzle.
+    pub puzzle: Option<Puzzle>,
+    /// Camera positions saved with the level.
+    pub bookmarks: [Option<Bookmark>; BOOKMARK_COUNT],
 }
 
 impl Grid {
@@ -104,6 +154,16 @@ impl Grid {
             height,
             cells: Vec::new(),
             tick_count: 0,
+            update_order: UpdateOrder::Quell,
+            events: None,
+            profile: None,
+            motion: None,
+            activity: None,
+            frames: None,
+            has_redirectors: false,
+            input: VecDeque::new(),
+            puzzle: None,
+            bookmarks: [None; BOOKMARK_COUNT],
         }
     }
 
@@ -117,6 +177,16 @@ impl Grid {
             height,
             cells: Vec::new(),
             tick_count: 0,
+            update_order: UpdateOrder::Quell,
+            events: None,
+            profile: None,
+            motion: None,
+            activity: None,
+            frames: None,
+            has_redirectors: false,
+            input: VecDeque::new(),
+            puzzle: None,
+            bookmarks: [None; BOOKMARK_COUNT],
         };
         g.init();
         g
@@ -219,6 +289,94 @@ impl Grid {
         }
     }
 
+    /// Records an event if event recording is enabled.
+    #[inline(always)]
+    pub fn emit(&mut self, event: UpdateEvent) {
+        if let Some(events) = &mut self.events {
+            events.push(event);
+        }
+    }
+
+    /// The recorded motion of the cell at the coordinate, if motion recording is enabled.
+    #[inline(always)]
+    pub fn motion_mut(&mut self, x: isize, y: isize) -> Option<&mut CellMotion> {
+        if !self.is_in_bounds(x, y) { return None; }
+        let width = self.width;
+        self.motion.as_mut()?.get_mut(y as usize * width + x as usize)
+    }
+
+    /// Records that the cell at the first coordinate moved to the second one.
+    #[inline(always)]
+    pub fn move_motion(&mut self, from_x: isize, from_y: isize, to_x: isize, to_y: isize) {
+        if let Some(motion) = self.motion_mut(from_x, from_y).map(mem::take) {
+            if let Some(target) = self.motion_mut(to_x, to_y) {
+                *target = motion.offset(to_x - from_x, to_y - from_y);
+            }
+        }
+    }
+
+    /// Records that the cells at the two coordinates swapped places.
+    #[inline(always)]
+    pub fn swap_motion(&mut self, ax: isize, ay: isize, bx: isize, by: isize) {
+        let a = self.motion_mut(ax, ay).map(mem::take).unwrap_or_default();
+        let b = self.motion_mut(bx, by).map(mem::take).unwrap_or_default();
+        if let Some(target) = self.motion_mut(ax, ay) { *target = b.offset(ax - bx, ay - by); }
+        if let Some(target) = self.motion_mut(bx, by) { *target = a.offset(bx - ax, by - ay); }
+    }
+
+    /// Starts counting the positions changed by an update, if activity counting is enabled.
+    pub fn begin_activity(&mut self) {
+        let len = self.cells.len();
+        if let Some(activity) = &mut self.activity {
+            activity.begin(len);
+        }
+    }
+
+    /// Records that the cell at the position changed, if activity counting is enabled.
+    /// Cells moved, rotated or removed during an update have to be recorded, even if the same cell takes their place.
+    #[inline(always)]
+    pub fn touch(&mut self, x: isize, y: isize) {
+        if !self.is_in_bounds(x, y) { return; }
+        let width = self.width;
+        if let Some(activity) = &mut self.activity {
+            activity.touch(y as usize * width + x as usize);
+        }
+    }
+
+    /// Counts the positions recorded since `begin_activity`, if activity counting is enabled.
+    pub fn end_activity(&mut self) {
+        if let Some(activity) = &mut self.activity {
+            activity.end();
+        }
+    }
+
+    /// Starts or stops the recordings that are enabled on another grid.
+    /// The update thread uses this to follow the shared grid, where the main thread enables them.
+    pub fn copy_recording(&mut self, other: &Grid) {
+        if self.events.is_some() != other.events.is_some() {
+            self.events = other.events.as_ref().map(|_| Vec::new());
+        }
+        if self.profile.is_some() != other.profile.is_some() {
+            self.profile = other.profile.as_ref().map(|_| Vec::new());
+        }
+        if self.motion.is_some() != other.motion.is_some() {
+            self.motion = other.motion.as_ref().map(|_| Vec::new());
+        }
+        if self.activity.is_some() != other.activity.is_some() {
+            self.activity = other.activity.as_ref().map(|_| Activity::new(ACTIVITY_WINDOW));
+        }
+        if self.frames.is_some() != other.frames.is_some() {
+            self.frames = other.frames.as_ref().map(|_| Vec::new());
+        }
+    }
+
+    /// Queues a direction for the player cells to move in.
+    pub fn queue_input(&mut self, dir: Direction) {
+        if self.input.len() < MAX_QUEUED_INPUTS {
+            self.input.push_back(dir);
+        }
+    }
+
     /// Iterates over every cell in the grid.
     pub fn for_each(&self, mut f: impl FnMut(isize, isize, Option<&Cell>)) {
         unsafe {
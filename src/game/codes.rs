@@ -1,7 +1,9 @@
+use std::collections::BTreeSet;
+
 use base64::{Engine, engine::general_purpose::STANDARD as base64};
 use libdeflater::{Compressor, CompressionLvl, Decompressor};
 
-use super::cells::{Cell, Grid};
+use super::{cells::{Bookmark, Cell, CellType, Grid, BOOKMARK_COUNT, MAX_GRID_SIZE}, cell_data::{CELL_DATA, all_cells, cell_data}, update::UpdateOrder, puzzle::{Puzzle, Goal}};
 
 pub fn export_q1(grid: &Grid) -> String {
     let mut result = String::new();
@@ -94,22 +96,160 @@ pub fn export_q2(grid: &Grid) -> String {
     let len = compressor.zlib_compress(&cell_result, &mut data).unwrap();
 
     result.push_str(&base64.encode(&data[..len]));
+    encode_metadata(grid, &mut result);
     result
 }
 
+/// Appends the level settings that differ from the defaults as `;key:value` pairs.
+fn encode_metadata(grid: &Grid, result: &mut String) {
+    // cells added by mods are saved with their texture, their ids depend on the installed mods
+    let mut mod_cells = BTreeSet::new();
+    grid.for_each(|_, _, cell| {
+        if let Some(cell) = cell {
+            mod_cells.insert(cell.id());
+        }
+    });
+    if let Some(puzzle) = &grid.puzzle {
+        mod_cells.extend(puzzle.inventory.keys());
+    }
+    mod_cells.retain(|&id| id as usize > CELL_DATA.len());
+    if !mod_cells.is_empty() {
+        result.push_str(";mods:");
+        result.push_str(&mod_cells.into_iter().map(|id| format!("{}={}", encode_num_62(id), cell_data(id).texture_name)).collect::<Vec<_>>().join(","));
+    }
+
+    if grid.update_order != UpdateOrder::Quell {
+        result.push_str(";order:");
+        result.push_str(grid.update_order.code());
+    }
+
+    if let Some(puzzle) = &grid.puzzle {
+        result.push_str(";goal:");
+        result.push_str(puzzle.goal.code());
+        result.push_str(";zone:");
+        result.push_str(&puzzle.zone_runs().into_iter().map(encode_num_62).collect::<Vec<_>>().join(","));
+        if !puzzle.inventory.is_empty() {
+            result.push_str(";inv:");
+            result.push_str(&puzzle.inventory.iter().map(|(&id, &count)| format!("{}={}", encode_num_62(id), encode_num_62(count))).collect::<Vec<_>>().join(","));
+        }
+    }
+
+    let bookmarks = grid.bookmarks.iter().enumerate()
+        .filter_map(|(i, bookmark)| bookmark.map(|b| format!("{}={}/{}/{}", i + 1, b.x, b.y, b.zoom)))
+        .collect::<Vec<_>>();
+    if !bookmarks.is_empty() {
+        result.push_str(";cam:");
+        result.push_str(&bookmarks.join(","));
+    }
+}
+
+/// Maps the cell types used in a code to the cell types of the game.
+/// Built-in cells keep their ids. Cells added by mods are looked up by texture in the `mods` table,
+/// codes without one use the ids of the currently loaded mods.
+fn cell_ids(mods: Option<&str>) -> Result<Vec<Option<CellType>>, &'static str> {
+    let mut ids = vec![None; CellType::MAX as usize + 1];
+    let Some(mods) = mods else {
+        for cell in all_cells() {
+            ids[cell.id as usize] = Some(cell.id);
+        }
+        return Ok(ids);
+    };
+
+    for cell in CELL_DATA {
+        ids[cell.id as usize] = Some(cell.id);
+    }
+    for item in mods.split(',').filter(|item| !item.is_empty()) {
+        let (id, texture) = item.split_once('=').ok_or("invalid mod table")?;
+        let id = parse_num_62(id)?;
+        if id <= CELL_DATA.len() || id > CellType::MAX as usize { return Err("invalid mod table"); }
+        let cell = all_cells().find(|cell| cell.texture_name == texture).ok_or("the level uses a mod that isn't installed")?;
+        ids[id] = Some(cell.id);
+    }
+    Ok(ids)
+}
+
+/// Looks up a cell type read from a code in the table from `cell_ids`.
+fn cell_id(ids: &[Option<CellType>], id: usize) -> Result<CellType, &'static str> {
+    ids.get(id).copied().flatten().ok_or("invalid cell type")
+}
+
+/// Reads the `key:value` pairs following the cell data. Unknown keys are ignored.
+fn decode_metadata<'a>(grid: &mut Grid, input: impl Iterator<Item = &'a str>, ids: &[Option<CellType>]) -> Result<(), &'static str> {
+    for pair in input {
+        let (key, value) = pair.split_once(':').ok_or("invalid metadata")?;
+        match key {
+            // read by `cell_ids` before the cells
+            "mods" => {},
+            "order" => grid.update_order = UpdateOrder::from_code(value).ok_or("unknown update order")?,
+            "goal" => puzzle_of(grid).goal = Goal::from_code(value).ok_or("unknown puzzle goal")?,
+            "zone" => {
+                let runs = value.split(',').map(parse_num_62).collect::<Result<Vec<_>, _>>()?;
+                puzzle_of(grid).set_zone_runs(&runs)?;
+            },
+            "inv" => {
+                let puzzle = puzzle_of(grid);
+                for item in value.split(',').filter(|item| !item.is_empty()) {
+                    let (id, count) = item.split_once('=').ok_or("invalid inventory")?;
+                    let id = cell_id(ids, parse_num_62(id)?)?;
+                    puzzle.inventory.insert(id, parse_num_62(count)?);
+                }
+            },
+            "cam" => {
+                for item in value.split(',').filter(|item| !item.is_empty()) {
+                    let (index, camera) = item.split_once('=').ok_or("invalid bookmark")?;
+                    let index = index.parse::<usize>().ok().filter(|i| (1..=BOOKMARK_COUNT).contains(i)).ok_or("invalid bookmark")?;
+                    let numbers = camera.split('/').map(|n| n.parse::<f32>().map_err(|_| "invalid bookmark")).collect::<Result<Vec<_>, _>>()?;
+                    let [x, y, zoom] = numbers[..] else { return Err("invalid bookmark") };
+                    if numbers.iter().any(|n| !n.is_finite()) || zoom <= 0.0 { return Err("invalid bookmark"); }
+                    grid.bookmarks[index - 1] = Some(Bookmark { x, y, zoom });
+                }
+            },
+            _ => {},
+        }
+    }
+    Ok(())
+}
+
+// internal helper
+fn puzzle_of(grid: &mut Grid) -> &mut Puzzle {
+    let (width, height) = (grid.width, grid.height);
+    grid.puzzle.get_or_insert_with(|| Puzzle::new(width, height))
+}
+
 pub fn import(input: &str) -> Result<Grid, &'static str> {
     let mut input = input.trim().split(';');
 
     let ty = input.next().ok_or("missing type specifier")?;
     match ty {
-        "Q1" => decode_q1(decode_num_62(input.next().ok_or("missing width")?.chars()), decode_num_62(input.next().ok_or("missing height")?.chars()), input),
-        "Q2" => decode_q2(decode_num_62(input.next().ok_or("missing width")?.chars()), decode_num_62(input.next().ok_or("missing height")?.chars()), input.next().ok_or("missing cell data")?),
+        "Q1" => decode_q1(decode_num_62(input.next().ok_or("missing width")?.chars()), decode_num_62(input.next().ok_or("missing height")?.chars()), input, &cell_ids(None)?),
+        "Q2" => {
+            let width = decode_num_62(input.next().ok_or("missing width")?.chars());
+            let height = decode_num_62(input.next().ok_or("missing height")?.chars());
+            let cells = input.next().ok_or("missing cell data")?;
+            let metadata = input.collect::<Vec<_>>();
+            let ids = cell_ids(metadata.iter().find_map(|pair| pair.strip_prefix("mods:")))?;
+            let mut grid = decode_q2(width, height, cells, &ids)?;
+            decode_metadata(&mut grid, metadata.into_iter(), &ids)?;
+            Ok(grid)
+        },
         "V3" => decode_v3(decode_num_74(input.next().ok_or("missing width")?.chars()), decode_num_74(input.next().ok_or("missing height")?.chars()), input.next().ok_or("missing cell data")?),
         _ => Err("unknown code type"),
     }
 }
 
-fn decode_q1<'a>(width: usize, height: usize, input: impl Iterator<Item = &'a str>) -> Result<Grid, &'static str> {
+/// Rejects grid sizes a level code can't have, before the grid is allocated.
+fn check_size(width: usize, height: usize) -> Result<(), &'static str> {
+    if width == 0 || height == 0 {
+        return Err("invalid grid size");
+    }
+    if width > MAX_GRID_SIZE || height > MAX_GRID_SIZE {
+        return Err("grid too large");
+    }
+    Ok(())
+}
+
+fn decode_q1<'a>(width: usize, height: usize, input: impl Iterator<Item = &'a str>, ids: &[Option<CellType>]) -> Result<Grid, &'static str> {
+    check_size(width, height)?;
     let mut grid = Grid::new(width, height);
 
     let mut cell_groups = Vec::new();
@@ -137,7 +277,7 @@ fn decode_q1<'a>(width: usize, height: usize, input: impl Iterator<Item = &'a st
         if !cell_str.is_empty() {
             let mut chars = cell_str.chars().collect::<Vec<_>>();
             let direction = chars.pop().unwrap().to_digit(10).unwrap() as u8;
-            let id = decode_num_62(chars.into_iter()) as u8;
+            let id = cell_id(ids, decode_num_62(chars.into_iter()))?;
             grid.set(x, y, Cell::new(id, direction.into()));
         }
         x += 1;
@@ -150,7 +290,8 @@ fn decode_q1<'a>(width: usize, height: usize, input: impl Iterator<Item = &'a st
     Ok(grid)
 }
 
-fn decode_q2(width: usize, height: usize, input: &str) -> Result<Grid, &'static str> {
+fn decode_q2(width: usize, height: usize, input: &str, ids: &[Option<CellType>]) -> Result<Grid, &'static str> {
+    check_size(width, height)?;
     let mut grid = Grid::new(width, height);
 
     let data = base64.decode(input).map_err(|_| "invalid base64")?;
@@ -184,7 +325,7 @@ fn decode_q2(width: usize, height: usize, input: &str) -> Result<Grid, &'static
     for cell in cell_arr {
         if cell != 0 {
             let cell = cell - 1;
-            grid.set(x, y, Cell::new((cell / 4) as u8, (cell % 4).into()));
+            grid.set(x, y, Cell::new(cell_id(ids, cell / 4)?, (cell % 4).into()));
         }
         x += 1;
         if x >= grid.width as isize {
@@ -197,6 +338,7 @@ fn decode_q2(width: usize, height: usize, input: &str) -> Result<Grid, &'static
 }
 
 fn decode_v3(width: usize, height: usize, cells: &str) -> Result<Grid, &'static str> {
+    check_size(width, height)?;
     let mut grid = Grid::new(width, height);
 
     let mut cell_index = 0;
@@ -294,6 +436,11 @@ fn encode_num_62<N: Into<usize>>(num: N) -> String {
     result.chars().rev().collect()
 }
 
+fn parse_num_62(text: &str) -> Result<usize, &'static str> {
+    if !text.chars().all(|c| NUMBER_KEY_62.contains(c)) { return Err("invalid number"); }
+    Ok(decode_num_62(text.chars()))
+}
+
 fn decode_num_62(chars: impl Iterator<Item = char>) -> usize {
     chars.fold(0, |acc, c| acc * 62 + NUMBER_KEY_62.find(c).unwrap())
 }
@@ -0,0 +1,54 @@
+use super::{cells::CellType, direction::Direction};
+
+/// Something notable that happened during an update step.
+///
+/// Events are only recorded if `Grid::events` is set, see `Grid::emit`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum UpdateEvent {
+    /// A generator created a new cell at the position.
+    Generated { x: isize, y: isize, id: CellType },
+    /// A cell got moved into a trash and was deleted.
+    Trashed { x: isize, y: isize, id: CellType },
+    /// An enemy at the position was destroyed together with the cell moved into it.
+    EnemyKilled { x: isize, y: isize, id: CellType },
+    /// A cell was rotated to a new direction.
+    Rotated { x: isize, y: isize, id: CellType },
+    /// A push starting at the position couldn't move the cells in front of it.
+    PushFailed { x: isize, y: isize, dir: Direction },
+}
+
+/// How a cell moved during an update step, used to animate it.
+///
+/// Motions are only recorded if `Grid::motion` is set.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct CellMotion {
+    /// Offset from the position the cell started at.
+    pub dx: i16,
+    pub dy: i16,
+    /// Quarter turns clockwise since the start, negative for counterclockwise.
+    pub turns: i8,
+}
+
+impl CellMotion {
+    /// The motion after moving the cell by an offset.
+    #[inline(always)]
+    pub fn offset(self, dx: isize, dy: isize) -> Self {
+        CellMotion {
+            dx: self.dx.wrapping_add(dx as i16),
+            dy: self.dy.wrapping_add(dy as i16),
+            turns: self.turns,
+        }
+    }
+
+    /// Adds a rotation by the direction, taking the shortest way.
+    #[inline(always)]
+    pub fn rotate(&mut self, by: Direction) {
+        let turns = match by {
+            Direction::Right => 0,
+            Direction::Down => 1,
+            Direction::Left => 2,
+            Direction::Up => -1,
+        };
+        self.turns = self.turns.wrapping_add(turns);
+    }
+}
@@ -0,0 +1,119 @@
+use std::{collections::HashMap, path::{Path, PathBuf}};
+
+use image::{imageops::{self, rotate90, rotate180, rotate270, FilterType}, RgbaImage};
+
+use super::{cells::{CellType, Grid}, cell_data::all_cells};
+
+/// Part of a grid as the lowest and highest corner.
+pub type Region = ((isize, isize), (isize, isize));
+
+/// Cell textures for rendering grids to images without a window.
+pub struct CellTextures {
+    background: RgbaImage,
+    /// Textures of every cell type, one per direction.
+    cells: HashMap<CellType, [RgbaImage; 4]>,
+}
+
+impl CellTextures {
+    /// Loads the textures of all cells, including the ones registered by mods.
+    pub fn load(resource_path: &Path) -> Result<Self, String> {
+        let background = image::open(resource_path.join("assets/background.png")).map_err(|e| format!("couldn't load background texture: {e}"))?.to_rgba8();
+        let mut cells = HashMap::new();
+        for cell in all_cells() {
+            let path = resource_path.join("assets/cells/".to_string() + cell.texture_name + ".png");
+            cells.insert(cell.id, create_rotated_textures(cell.sides, path)?);
+        }
+        Ok(CellTextures { background, cells })
+    }
+
+    /// Width of the textures in pixels.
+    pub fn size(&self) -> u32 {
+        self.background.width()
+    }
+
+    /// The textures scaled to a size, so they don't have to be scaled for every rendered image.
+    pub fn scaled(&self, size: u32) -> CellTextures {
+        let scale = |image: &RgbaImage| imageops::resize(image, size, size, FilterType::Nearest);
+        CellTextures {
+            background: scale(&self.background),
+            cells: self.cells.iter().map(|(&id, textures)| (id, textures.each_ref().map(scale))).collect(),
+        }
+    }
+}
+
+/// Limits a region to the cells of a grid, or returns the whole grid if no region is given.
+pub fn clamp_region(grid: &Grid, region: Option<Region>) -> Result<Region, String> {
+    let grid_max = (grid.width as isize - 1, grid.height as isize - 1);
+    let Some((min, max)) = region else { return Ok(((0, 0), grid_max)) };
+
+    let min = (min.0.max(0), min.1.max(0));
+    let max = (max.0.min(grid_max.0), max.1.min(grid_max.1));
+    if min.0 > max.0 || min.1 > max.1 {
+        return Err(format!("the region doesn't overlap the {}x{} grid", grid.width, grid.height));
+    }
+    Ok((min, max))
+}
+
+/// Size in pixels of an image of the region, if it can be allocated.
+pub fn image_size((min, max): Region, cell_size: u32) -> Result<(u32, u32), String> {
+    let too_large = || "the image would be too large".to_string();
+    let width = u32::try_from(max.0 - min.0 + 1).ok().and_then(|w| w.checked_mul(cell_size)).ok_or_else(too_large)?;
+    let height = u32::try_from(max.1 - min.1 + 1).ok().and_then(|h| h.checked_mul(cell_size)).ok_or_else(too_large)?;
+    let bytes = (width as usize).checked_mul(height as usize).and_then(|pixels| pixels.checked_mul(4)).ok_or_else(too_large)?;
+    if bytes > isize::MAX as usize {
+        return Err(too_large());
+    }
+    Ok((width, height))
+}
+
+/// Renders the cells between the lowest and highest corner of a grid, or the whole grid if no region is given.
+/// Every cell is `cell_size` pixels wide, positions outside of the grid are left transparent.
+pub fn render_grid(grid: &Grid, textures: &CellTextures, region: Option<Region>, cell_size: u32) -> Result<RgbaImage, String> {
+    let (min, max) = region.unwrap_or(((0, 0), (grid.width as isize - 1, grid.height as isize - 1)));
+    let (width, height) = image_size((min, max), cell_size)?;
+    if textures.size() != cell_size {
+        return render_grid(grid, &textures.scaled(cell_size), region, cell_size);
+    }
+
+    let mut image = RgbaImage::new(width, height);
+    for y in min.1..=max.1 {
+        for x in min.0..=max.0 {
+            if !grid.is_in_bounds(x, y) { continue; }
+            // the grid's y axis points up
+            let px = (x - min.0) as i64 * cell_size as i64;
+            let py = (max.1 - y) as i64 * cell_size as i64;
+            imageops::replace(&mut image, &textures.background, px, py);
+            if let Some(cell) = grid.get(x, y) {
+                if let Some(texture) = textures.cells.get(&cell.id()) {
+                    imageops::overlay(&mut image, &texture[usize::from(cell.direction())], px, py);
+                }
+            }
+        }
+    }
+    Ok(image)
+}
+
+/// Renders a grid and saves it as a PNG image, the region is limited to the grid.
+pub fn export_png(grid: &Grid, textures: &CellTextures, region: Option<Region>, cell_size: u32, path: &Path) -> Result<(), String> {
+    let region = clamp_region(grid, region)?;
+    render_grid(grid, textures, Some(region), cell_size)?
+        .save_with_format(path, image::ImageFormat::Png)
+        .map_err(|e| format!("couldn't save image: {e}"))
+}
+
+/// Loads a texture and rotates it for every direction.
+/// Textures of cells with less than four sides are repeated.
+pub fn create_rotated_textures(amount: usize, path: PathBuf) -> Result<[RgbaImage; 4], String> {
+    let first_texture = image::open(&path).map_err(|e| format!("couldn't load texture {}: {e}", path.display()))?.to_rgba8();
+    let mut textures = [first_texture.clone(), first_texture.clone(), first_texture.clone(), first_texture];
+    for (i, img) in textures.iter_mut().enumerate() {
+        match i % amount {
+            0 => {},
+            1 => *img = rotate90(img),
+            2 => *img = rotate180(img),
+            3 => *img = rotate270(img),
+            _ => unreachable!(),
+        }
+    }
+    Ok(textures)
+}
@@ -0,0 +1,66 @@
+use super::{cells::{Cell, CellType, Grid}, cell_data::cell_data, direction::Direction};
+
+/// Which cells find and replace matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CellQuery {
+    pub id: CellType,
+    /// Only matches cells looking in the direction if set.
+    pub direction: Option<Direction>,
+    /// Only matches cells between the lowest and highest corner if set.
+    pub region: Option<((isize, isize), (isize, isize))>,
+}
+
+impl CellQuery {
+    pub fn new(id: CellType) -> Self {
+        CellQuery { id, direction: None, region: None }
+    }
+
+    pub fn matches(&self, x: isize, y: isize, cell: &Cell) -> bool {
+        if cell.id() != self.id { return false; }
+        if let Some(direction) = self.direction {
+            // directions that look the same match, like in `Cell::looks_like`
+            let sides = cell_data(self.id).sides as u8;
+            if cell.direction() % sides != direction % sides { return false; }
+        }
+        match self.region {
+            Some((min, max)) => x >= min.0 && y >= min.1 && x <= max.0 && y <= max.1,
+            None => true,
+        }
+    }
+
+    /// Positions of all matching cells on the grid.
+    pub fn find(&self, grid: &Grid) -> Vec<(isize, isize)> {
+        let mut found = Vec::new();
+        grid.for_each(|x, y, cell| {
+            if cell.is_some_and(|cell| self.matches(x, y, cell)) {
+                found.push((x, y));
+            }
+        });
+        found
+    }
+}
+
+/// What matching cells are replaced with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Replacement {
+    /// Changes the type and the direction, `None` keeps them.
+    Change(Option<CellType>, Option<Direction>),
+    Delete,
+}
+
+impl Replacement {
+    pub fn apply(self, cell: &Cell) -> Option<Cell> {
+        match self {
+            Replacement::Change(id, direction) => Some(Cell::new(id.unwrap_or(cell.id()), direction.unwrap_or(cell.direction()))),
+            Replacement::Delete => None,
+        }
+    }
+
+    /// Checks if the replacement changes the cells matched by the query.
+    pub fn changes(self, query: &CellQuery) -> bool {
+        match self {
+            Replacement::Change(id, direction) => id.is_some_and(|id| id != query.id) || direction.is_some(),
+            Replacement::Delete => true,
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use std::{fs, path::{Path, PathBuf}, time::Instant};
+
+use super::{codes::import, update::{update, SubtickProfile}, direction::Direction, export::{clamp_region, export_png, CellTextures, Region}, recording::{Recording, RecordOptions}};
+
+/// Options for simulating a level without opening a window.
+#[derive(Debug, Clone)]
+pub struct HeadlessOptions {
+    /// File containing the level code.
+    pub level: PathBuf,
+    /// Amount of ticks to simulate.
+    pub ticks: u32,
+    /// Whether to print the time spent in every subtick.
+    pub profile: bool,
+    /// Player input for every tick, `None` if no key is pressed.
+    pub input: Vec<Option<Direction>>,
+    /// Image the grid is saved to after the simulation.
+    pub export: Option<PathBuf>,
+    /// Animation the simulated ticks are recorded to, a GIF or an animated PNG.
+    pub record: Option<PathBuf>,
+    /// Tick the recording starts at.
+    pub record_from: u32,
+    /// Ticks skipped between two frames of the recording.
+    pub frame_skip: u32,
+    /// Time every frame of the recording is shown in milliseconds.
+    pub frame_time: u32,
+    /// Pixels per cell of the exported image and the recording.
+    pub cell_size: u32,
+    /// Part of the grid that is exported as the lowest and highest corner, the whole grid if not set.
+    pub region: Option<Region>,
+}
+
+impl HeadlessOptions {
+    /// Parses the command line arguments following `--headless`.
+    pub fn parse(args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut level = None;
+        let mut ticks = 100;
+        let mut profile = false;
+        let mut input = Vec::new();
+        let mut export = None;
+        let mut record = None;
+        let mut record_from = 0;
+        let mut frame_skip = 0;
+        let mut frame_time = RecordOptions::default().frame_time;
+        let mut cell_size = 16;
+        let mut region = None;
+
+        let mut args = args;
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--ticks" => {
+                    let value = args.next().ok_or("missing value for --ticks")?;
+                    ticks = value.parse().map_err(|_| format!("invalid tick count: {value}"))?;
+                },
+                "--profile" => profile = true,
+                "--input" => {
+                    let value = args.next().ok_or("missing value for --input")?;
+                    input = parse_input(&value)?;
+                },
+                "--export" => export = Some(PathBuf::from(args.next().ok_or("missing value for --export")?)),
+                "--record" => record = Some(PathBuf::from(args.next().ok_or("missing value for --record")?)),
+                "--record-from" => {
+                    let value = args.next().ok_or("missing value for --record-from")?;
+                    record_from = value.parse().map_err(|_| format!("invalid tick: {value}"))?;
+                },
+                "--frame-skip" => {
+                    let value = args.next().ok_or("missing value for --frame-skip")?;
+                    frame_skip = value.parse().map_err(|_| format!("invalid frame skip: {value}"))?;
+                },
+                "--frame-time" => {
+                    let value = args.next().ok_or("missing value for --frame-time")?;
+                    frame_time = value.parse().map_err(|_| format!("invalid frame time: {value}"))?;
+                },
+                "--cell-size" => {
+                    let value = args.next().ok_or("missing value for --cell-size")?;
+                    cell_size = value.parse().ok().filter(|&size| size > 0).ok_or(format!("invalid cell size: {value}"))?;
+                },
+                "--region" => {
+                    let value = args.next().ok_or("missing value for --region")?;
+                    region = Some(parse_region(&value)?);
+                },
+                _ if arg.starts_with("--") => return Err(format!("unknown option: {arg}")),
+                _ => level = Some(PathBuf::from(arg)),
+            }
+        }
+
+        Ok(HeadlessOptions {
+            level: level.ok_or("missing level file")?,
+            ticks,
+            profile,
+            input,
+            export,
+            record,
+            record_from,
+            frame_skip,
+            frame_time,
+            cell_size,
+            region,
+        })
+    }
+}
+
+/// Parses an input sequence like `RR.UL`, one character per tick.
+/// `R`, `D`, `L` and `U` are the arrow keys, `.` means no key is pressed.
+fn parse_input(sequence: &str) -> Result<Vec<Option<Direction>>, String> {
+    sequence.chars().map(|c| match c.to_ascii_uppercase() {
+        'R' => Ok(Some(Direction::Right)),
+        'D' => Ok(Some(Direction::Down)),
+        'L' => Ok(Some(Direction::Left)),
+        'U' => Ok(Some(Direction::Up)),
+        '.' => Ok(None),
+        _ => Err(format!("invalid input: {c}")),
+    }).collect()
+}
+
+/// Parses a region like `0,0,9,4`, two corners in any order.
+fn parse_region(value: &str) -> Result<Region, String> {
+    let numbers = value.split(',')
+        .map(|n| n.trim().parse::<isize>().map_err(|_| format!("invalid region: {value}")))
+        .collect::<Result<Vec<_>, _>>()?;
+    let [x1, y1, x2, y2] = numbers[..] else { return Err(format!("invalid region: {value}")) };
+    Ok(((x1.min(x2), y1.min(y2)), (x1.max(x2), y1.max(y2))))
+}
+
+/// Simulates a level and prints the results to stdout.
+/// The textures for exporting images and recordings are loaded from the resource path.
+pub fn run_headless(options: HeadlessOptions, resource_path: &Path) -> Result<(), String> {
+    let code = fs::read_to_string(&options.level).map_err(|e| format!("couldn't read level file: {e}"))?;
+    let mut grid = import(&code)?;
+    // fail before running the ticks instead of after
+    let region = options.region.map(|region| clamp_region(&grid, Some(region))).transpose()?;
+    if options.profile {
+        grid.profile = Some(Vec::new());
+    }
+
+    // accumulated over all ticks, in order of first execution
+    let mut subticks: Vec<SubtickProfile> = Vec::new();
+    let mut solved_at = None;
+    let problem = grid.puzzle.as_ref().and_then(|puzzle| puzzle.goal.problem(&grid));
+
+    let mut recording = options.record.as_ref().map(|_| Recording::new(RecordOptions {
+        frame_skip: options.frame_skip,
+        cell_size: options.cell_size,
+        frame_time: options.frame_time,
+        region,
+    }));
+    if let Some(recording) = &mut recording {
+        if options.record_from == 0 {
+            recording.capture(&grid);
+        }
+    }
+
+    let start = Instant::now();
+    for tick in 0..options.ticks as usize {
+        if let Some(&Some(dir)) = options.input.get(tick) {
+            grid.queue_input(dir);
+        }
+        update(&mut grid);
+        if let Some(recording) = &mut recording {
+            if grid.tick_count >= options.record_from {
+                recording.capture(&grid);
+            }
+        }
+        if let Some(puzzle) = &grid.puzzle {
+            if solved_at.is_none() && problem.is_none() && puzzle.goal.is_reached(&grid) {
+                solved_at = Some(grid.tick_count);
+            }
+        }
+        if let Some(profile) = &grid.profile {
+            for subtick in profile {
+                if let Some(total) = subticks.iter_mut().find(|s| s.name == subtick.name) {
+                    total.time += subtick.time;
+                    total.cells += subtick.cells;
+                }
+                else {
+                    subticks.push(subtick.clone());
+                }
+            }
+        }
+    }
+    let elapsed = start.elapsed().as_secs_f32() * 1000.0;
+
+    println!("Simulated {} ticks in {elapsed:.3} ms ({:.3} ms per tick)", options.ticks, elapsed / options.ticks.max(1) as f32);
+    if grid.puzzle.is_some() {
+        match (solved_at, problem) {
+            (_, Some(problem)) => println!("Puzzle can't be solved: {problem}"),
+            (Some(tick), None) => println!("Puzzle solved after {tick} ticks"),
+            (None, None) => println!("Puzzle not solved"),
+        }
+    }
+
+    if options.profile {
+        println!();
+        println!("{:<20} {:>12} {:>12} {:>12}", "subtick", "total ms", "ms/tick", "cells/tick");
+        let ticks = options.ticks.max(1) as f32;
+        for subtick in subticks {
+            println!("{:<20} {:>12.3} {:>12.4} {:>12.1}", subtick.name, subtick.time, subtick.time / ticks, subtick.cells as f32 / ticks);
+        }
+    }
+
+    if let Some(path) = &options.export {
+        let textures = CellTextures::load(resource_path)?;
+        export_png(&grid, &textures, region, options.cell_size, path)?;
+        println!("Exported image to {}", path.display());
+    }
+
+    if let (Some(path), Some(recording)) = (&options.record, &recording) {
+        let textures = CellTextures::load(resource_path)?;
+        recording.save(&textures, path)?;
+        println!("Recorded {} frames to {}", recording.frame_count(), path.display());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,128 @@
+use std::{collections::{HashMap, HashSet}, fs, path::{Path, PathBuf}};
+
+use image::{Rgba, RgbaImage};
+
+use super::{cells::{CellType, Grid}, codes::import, settings::data_dir};
+
+/// Color of positions without a cell in thumbnails.
+const THUMBNAIL_BACKGROUND: Rgba<u8> = Rgba([40, 40, 40, 255]);
+
+/// A level of a level pack.
+#[derive(Debug, Clone)]
+pub struct Level {
+    /// File name without the extension, used to remember the progress.
+    pub name: String,
+    pub grid: Grid,
+}
+
+/// The levels in the `levels` folder and which of them are solved.
+///
+/// Levels are `.txt` files containing a level code, played in the order of their file names.
+/// The progress is saved in `progress.sav` in the data directory, one solved level name per line.
+#[derive(Debug, Clone)]
+pub struct LevelPack {
+    pub levels: Vec<Level>,
+    solved: HashSet<String>,
+    /// Not set if there is no data directory, the progress is lost then.
+    progress_path: Option<PathBuf>,
+}
+
+impl LevelPack {
+    /// Loads all levels in the folder. Levels that can't be imported are skipped.
+    pub fn load(resource_path: &Path) -> Self {
+        let folder = resource_path.join("levels");
+
+        let mut paths = fs::read_dir(&folder)
+            .map(|entries| entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+                .collect::<Vec<_>>())
+            .unwrap_or_default();
+        paths.sort();
+
+        let mut levels = Vec::new();
+        for path in paths {
+            let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+            match fs::read_to_string(&path).map_err(|e| e.to_string()).and_then(|code| import(&code).map_err(str::to_string)) {
+                Ok(grid) => levels.push(Level { name, grid }),
+                Err(err) => eprintln!("{}: {err}", path.display()),
+            }
+        }
+
+        let progress_path = data_dir().map(|dir| dir.join("progress.sav"));
+        let solved = progress_path.as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|progress| progress.lines().map(str::to_string).collect())
+            .unwrap_or_default();
+
+        LevelPack { levels, solved, progress_path }
+    }
+
+    pub fn is_solved(&self, index: usize) -> bool {
+        self.solved.contains(&self.levels[index].name)
+    }
+
+    pub fn solved_count(&self) -> usize {
+        (0..self.levels.len()).filter(|&i| self.is_solved(i)).count()
+    }
+
+    /// Marks a level as solved and saves the progress.
+    pub fn set_solved(&mut self, index: usize) {
+        if self.solved.insert(self.levels[index].name.clone()) {
+            let Some(path) = &self.progress_path else { return };
+            let mut names = self.solved.iter().cloned().collect::<Vec<_>>();
+            names.sort();
+            let result = path.parent().map_or(Ok(()), fs::create_dir_all).and_then(|_| fs::write(path, names.join("\n")));
+            if let Err(err) = result {
+                eprintln!("couldn't save progress: {err}");
+            }
+        }
+    }
+}
+
+/// Renders a grid with one pixel per cell, using a single color for every cell type.
+pub fn render_thumbnail(grid: &Grid, colors: &HashMap<CellType, Rgba<u8>>) -> RgbaImage {
+    let mut image = RgbaImage::from_pixel(grid.width as u32, grid.height as u32, THUMBNAIL_BACKGROUND);
+    grid.for_each(|x, y, cell| {
+        if let Some(cell) = cell {
+            let color = colors.get(&cell.id()).copied().unwrap_or(THUMBNAIL_BACKGROUND);
+            // the grid's y axis points up
+            image.put_pixel(x as u32, (grid.height as isize - 1 - y) as u32, color);
+        }
+    });
+    image
+}
+
+/// Renders a grid like `render_thumbnail`, but with at most `size` pixels on the longer side.
+/// Larger grids are sampled, every pixel shows one of the cells it covers.
+pub fn render_minimap(grid: &Grid, colors: &HashMap<CellType, Rgba<u8>>, size: u32) -> RgbaImage {
+    let scale = (size as f64 / grid.width.max(grid.height) as f64).min(1.0);
+    let width = ((grid.width as f64 * scale) as u32).max(1);
+    let height = ((grid.height as f64 * scale) as u32).max(1);
+    RgbaImage::from_fn(width, height, |px, py| {
+        let x = px as usize * grid.width / width as usize;
+        // the grid's y axis points up
+        let y = grid.height - 1 - py as usize * grid.height / height as usize;
+        match grid.get(x as isize, y as isize) {
+            Some(cell) => colors.get(&cell.id()).copied().unwrap_or(THUMBNAIL_BACKGROUND),
+            None => THUMBNAIL_BACKGROUND,
+        }
+    })
+}
+
+/// Average color of the visible pixels of a texture.
+pub fn average_color(image: &RgbaImage) -> Rgba<u8> {
+    let mut sum = [0u64; 3];
+    let mut weight = 0u64;
+    for pixel in image.pixels() {
+        let alpha = pixel[3] as u64;
+        for (s, &c) in sum.iter_mut().zip(&pixel.0[..3]) {
+            *s += c as u64 * alpha;
+        }
+        weight += alpha;
+    }
+    if weight == 0 {
+        return Rgba([0, 0, 0, 0]);
+    }
+    Rgba([(sum[0] / weight) as u8, (sum[1] / weight) as u8, (sum[2] / weight) as u8, 255])
+}
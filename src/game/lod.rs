@@ -0,0 +1,111 @@
+use std::{collections::HashMap, mem};
+
+use image::{Rgba, RgbaImage};
+use speedy2d::{Graphics2D, dimen::Vector2, image::{ImageDataType, ImageHandle, ImageSmoothingMode}};
+
+use super::{cells::{Cell, CellType, Grid}, export::{render_grid, CellTextures}};
+
+/// Cells per side of a chunk.
+pub const CHUNK_SIZE: isize = 32;
+
+/// Color of empty cells when drawing cells as solid colors.
+const EMPTY_COLOR: Rgba<u8> = Rgba([40, 40, 40, 255]);
+
+/// Renders the cells between the lowest and highest corner into the image, using the average color of every cell type.
+/// If there are more cells than pixels, every pixel shows one of the cells it covers.
+pub fn render_colors(image: &mut RgbaImage, grid: &Grid, colors: &HashMap<CellType, Rgba<u8>>, min: (isize, isize), max: (isize, isize)) {
+    let (width, height) = image.dimensions();
+    let cells_x = (max.0 - min.0 + 1) as u64;
+    let cells_y = (max.1 - min.1 + 1) as u64;
+    for (px, py, pixel) in image.enumerate_pixels_mut() {
+        let x = min.0 + (px as u64 * cells_x / width as u64) as isize;
+        // the grid's y axis points up
+        let y = max.1 - (py as u64 * cells_y / height as u64) as isize;
+        *pixel = match grid.get(x, y) {
+            Some(cell) => colors.get(&cell.id()).copied().unwrap_or(EMPTY_COLOR),
+            None => EMPTY_COLOR,
+        };
+    }
+}
+
+/// Texture of the cells drawn as solid colors, with at most as many pixels as the screen.
+/// The texture is only created again when its pixels change.
+#[derive(Default)]
+pub struct ColorCache {
+    image: Option<ImageHandle>,
+    /// Pixels of the texture.
+    pixels: RgbaImage,
+    /// Pixels of the current frame, kept to reuse the memory.
+    next: RgbaImage,
+}
+
+impl ColorCache {
+    /// The texture of the cells between the lowest and highest corner, with at most `max_size` pixels.
+    pub fn get(&mut self, g: &mut Graphics2D, grid: &Grid, colors: &HashMap<CellType, Rgba<u8>>, min: (isize, isize), max: (isize, isize), max_size: (u32, u32)) -> &ImageHandle {
+        let width = ((max.0 - min.0 + 1) as u32).clamp(1, max_size.0.max(1));
+        let height = ((max.1 - min.1 + 1) as u32).clamp(1, max_size.1.max(1));
+        if self.next.dimensions() != (width, height) {
+            self.next = RgbaImage::new(width, height);
+        }
+        render_colors(&mut self.next, grid, colors, min, max);
+
+        if self.image.is_none() || self.next != self.pixels {
+            mem::swap(&mut self.pixels, &mut self.next);
+            self.image = Some(g.create_image_from_raw_pixels(ImageDataType::RGBA, ImageSmoothingMode::NearestNeighbor, Vector2::new(width, height), self.pixels.as_raw()).unwrap());
+        }
+        self.image.as_ref().unwrap()
+    }
+}
+
+struct Chunk {
+    /// The cells the texture was rendered from.
+    cells: Vec<Option<Cell>>,
+    image: ImageHandle,
+    used: bool,
+}
+
+/// Textures of square parts of the grid, only rendered again when their cells change.
+#[derive(Default)]
+pub struct ChunkCache {
+    chunks: HashMap<(isize, isize), Chunk>,
+    /// Size of the grid the chunks were rendered from.
+    grid_size: (usize, usize),
+}
+
+impl ChunkCache {
+    /// The texture of the chunk at the chunk position, rendered with the cell textures if it changed.
+    pub fn get(&mut self, g: &mut Graphics2D, grid: &Grid, textures: &CellTextures, chunk: (isize, isize)) -> &ImageHandle {
+        if self.grid_size != (grid.width, grid.height) {
+            self.chunks.clear();
+            self.grid_size = (grid.width, grid.height);
+        }
+
+        let min = (chunk.0 * CHUNK_SIZE, chunk.1 * CHUNK_SIZE);
+        let max = (min.0 + CHUNK_SIZE - 1, min.1 + CHUNK_SIZE - 1);
+        let mut cells = Vec::with_capacity((CHUNK_SIZE * CHUNK_SIZE) as usize);
+        for y in min.1..=max.1 {
+            for x in min.0..=max.0 {
+                cells.push(grid.get(x, y).clone());
+            }
+        }
+
+        let dirty = self.chunks.get(&chunk).is_none_or(|cached| cached.cells != cells);
+        if dirty {
+            let image = render_grid(grid, textures, Some((min, max)), textures.size()).unwrap();
+            let image = g.create_image_from_raw_pixels(ImageDataType::RGBA, ImageSmoothingMode::Linear, Vector2::new(image.width(), image.height()), &image.into_raw()).unwrap();
+            self.chunks.insert(chunk, Chunk { cells, image, used: true });
+        }
+
+        let chunk = self.chunks.get_mut(&chunk).unwrap();
+        chunk.used = true;
+        &chunk.image
+    }
+
+    /// Drops the chunks that weren't drawn since the last call, so only visible chunks are kept.
+    pub fn evict_unused(&mut self) {
+        self.chunks.retain(|_, chunk| chunk.used);
+        for chunk in self.chunks.values_mut() {
+            chunk.used = false;
+        }
+    }
+}
@@ -1,5 +1,7 @@
+use std::mem;
+
 use speedy2d::dimen::Vector2;
-use crate::game::{direction::Direction, cells::{Cell, Grid}, cell_data::{WALL, SLIDE, MOVER, ORIENTATOR, TRASH, ENEMY, PULLER, PULLSHER, MIRROR, CROSSMIRROR, TRASHMOVER, SPEED, MOVLER, ONE_DIR, SLIDE_WALL, TRASHPULLER, GHOST, SUCKER}};
+use crate::game::{direction::Direction, cells::{Cell, Grid}, events::{UpdateEvent, CellMotion}, cell_data::{cell_data, REDIRECTOR}};
 
 /// A force a cell is moved with.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -33,32 +35,31 @@ impl PushResult {
 /// Checks if a cell can move in a certain direction with the given force.
 #[inline]
 pub fn can_move(cell: &Cell, direction: Direction, force: MoveForce) -> bool {
-    match cell.id() {
-        WALL | GHOST => false,
-        SLIDE | SLIDE_WALL if cell.direction() % 2 != direction % 2 => false,
-        ONE_DIR if cell.direction() != direction => false,
-        MIRROR if force == MoveForce::Swap && cell.direction() % 2 == direction % 2 => false,
-        CROSSMIRROR if force == MoveForce::Swap => false,
-        _ => true,
-    }
+    cell_data(cell.id()).behavior.can_move(cell, direction, force)
 }
 
 /// Checks if a cell is a trash in the direction it is being pushed.
 #[inline]
 pub fn is_trash(cell: &Cell, direction: Direction) -> bool {
-    match cell.id() {
-        TRASH | ENEMY => true,
-        TRASHMOVER if cell.direction() == direction.flip() => true,
-        TRASHPULLER if cell.direction() == direction => true,
-        SUCKER if cell.direction() == direction.flip() => true,
-        _ => false,
-    }
+    cell_data(cell.id()).behavior.is_trash(cell, direction)
+}
+
+/// Checks if a cell is an enemy.
+#[inline]
+pub fn is_enemy(cell: &Cell) -> bool {
+    cell_data(cell.id()).behavior.is_enemy(cell)
 }
 
 /// Checks if a cell can be generated.
 #[inline(always)]
 pub fn can_generate(cell: &Cell) -> bool {
-    cell.id() != GHOST
+    cell_data(cell.id()).behavior.can_generate(cell)
+}
+
+/// The force a cell adds when it's moved in a direction.
+#[inline(always)]
+pub fn force_of(cell: &Cell, direction: Direction) -> isize {
+    cell_data(cell.id()).behavior.force(cell, direction)
 }
 
 /// Pushes the specified cell in a direction. Returns whether the cell was moved.
@@ -73,23 +74,16 @@ pub fn push(grid: &mut Grid, x: isize, y: isize, dir: Direction, mut force: usiz
 
     // Check if the cell can be pushed.
     loop {
-        if !grid.is_in_bounds(tx, ty) { return PushResult::NotMoved; }
+        if !grid.is_in_bounds(tx, ty) { return push_failed(grid, x, y, orig_dir); }
 
         let cell = grid.get(tx, ty);
         if let Some(cell) = cell {
-            if cell.id() == MOVER || cell.id() == PULLER || cell.id() == PULLSHER || cell.id() == TRASHMOVER || cell.id() == SPEED || cell.id() == MOVLER {
-                if cell.direction() == dir {
-                    force += 1;
-                }
-                else if cell.direction() == dir.flip() {
-                    force -= 1;
-                }
-            }
+            force = force.wrapping_add_signed(force_of(cell, dir));
 
             if is_trash(cell, dir) { break; }
 
             if !can_move(cell, dir, MoveForce::Push) {
-                return PushResult::NotMoved;
+                return push_failed(grid, x, y, orig_dir);
             }
 
             let Vector2 { x: ox, y: oy } = dir.to_vector();
@@ -100,7 +94,7 @@ pub fn push(grid: &mut Grid, x: isize, y: isize, dir: Direction, mut force: usiz
             break;
         }
 
-        if force == 0 { return PushResult::NotMoved; }
+        if force == 0 { return push_failed(grid, x, y, orig_dir); }
         if tx == x && ty == y && dir == orig_dir { break; }
     }
 
@@ -122,24 +116,37 @@ pub fn push(grid: &mut Grid, x: isize, y: isize, dir: Direction, mut force: usiz
     let mut x = x;
     let mut y = y;
     let mut next_cell = pushing;
+    // motion of `next_cell`, a replacement cell comes from behind the first cell
+    let mut next_motion = CellMotion::default();
     let mut push_result = PushResult::Trashed;
+    let Vector2 { x: ox, y: oy } = dir.to_vector();
     loop {
         if let Some(ref mut cell) = next_cell {
             // Update mover cell `.updated`.
-            if (cell.id() == MOVER || cell.id() == PULLER || cell.id() == PULLSHER || cell.id() == TRASHMOVER || cell.id() == SPEED || cell.id() == MOVLER) && cell.direction() == dir && setupdated {
+            if setupdated && force_of(cell, dir) > 0 {
                 cell.set_updated(true);
             }
         }
 
         if let Some(cell) = grid.get_mut(x, y) {
             // When trash then break.
-            if cell.id() == ENEMY {
+            if is_enemy(cell) {
                 // Cell is deleted and enemy destroyed.
+                if let Some(killer) = &next_cell {
+                    grid.emit(UpdateEvent::EnemyKilled { x, y, id: killer.id() });
+                }
                 grid.delete(x, y);
+                grid.touch(x, y);
+                if let Some(motion) = grid.motion_mut(x, y) {
+                    *motion = CellMotion::default();
+                }
                 break;
             }
             else if is_trash(cell, dir) {
                 // Cell is trashed.
+                if let Some(trashed) = &next_cell {
+                    grid.emit(UpdateEvent::Trashed { x, y, id: trashed.id() });
+                }
                 break;
             }
         }
@@ -147,11 +154,20 @@ pub fn push(grid: &mut Grid, x: isize, y: isize, dir: Direction, mut force: usiz
         // Push cell and store current one in next push replacement.
         push_result = PushResult::Moved;
         let old_cell = grid.take(x, y);
+        let moved = next_cell.is_some();
+        if moved || old_cell.is_some() {
+            grid.touch(x, y);
+        }
         grid.set_cell(x, y, next_cell);
+        if let Some(motion) = grid.motion_mut(x, y) {
+            next_motion = mem::replace(motion, next_motion.offset(ox, oy));
+        }
+        if moved {
+            redirect(grid, x, y);
+        }
         next_cell = old_cell;
         if tx == x && ty == y { break; }
 
-        let Vector2 { x: ox, y: oy } = dir.to_vector();
         x += ox;
         y += oy;
     }
@@ -159,6 +175,81 @@ pub fn push(grid: &mut Grid, x: isize, y: isize, dir: Direction, mut force: usiz
     push_result
 }
 
+/// Why a push stopped, see `push_preview`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PushStop {
+    /// The chain ends in empty space.
+    Empty,
+    /// The chain ends in a trash cell at the position.
+    Trash(isize, isize),
+    /// The chain reaches the end of the grid.
+    OutOfBounds,
+    /// The cell at the position can't be pushed in the direction.
+    Immovable(isize, isize),
+    /// The force of the chain drops to zero at the position.
+    NoForce(isize, isize),
+    /// The chain loops back to the first cell.
+    Loop,
+}
+
+/// A cell in the chain of a push, see `push_preview`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ChainStep {
+    pub x: isize,
+    pub y: isize,
+    /// The force after adding the force of the cell.
+    pub force: isize,
+}
+
+/// What would happen when pushing a cell.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PushPreview {
+    pub chain: Vec<ChainStep>,
+    pub result: PushResult,
+    pub stop: PushStop,
+}
+
+/// Walks the chain like `push` does, without changing the grid.
+/// Redirectors are not taken into account, because they only rotate cells after they moved.
+pub fn push_preview(grid: &Grid, x: isize, y: isize, dir: Direction, force: usize) -> PushPreview {
+    let mut chain = Vec::new();
+    let mut force = force as isize;
+    let mut tx = x;
+    let mut ty = y;
+
+    let stop = loop {
+        if !grid.is_in_bounds(tx, ty) { break PushStop::OutOfBounds; }
+
+        let Some(cell) = grid.get(tx, ty) else { break PushStop::Empty };
+        if is_trash(cell, dir) { break PushStop::Trash(tx, ty); }
+
+        force += force_of(cell, dir);
+        chain.push(ChainStep { x: tx, y: ty, force });
+
+        if !can_move(cell, dir, MoveForce::Push) { break PushStop::Immovable(tx, ty); }
+        if force <= 0 { break PushStop::NoForce(tx, ty); }
+
+        let Vector2 { x: ox, y: oy } = dir.to_vector();
+        tx += ox;
+        ty += oy;
+        if tx == x && ty == y { break PushStop::Loop; }
+    };
+
+    let result = match stop {
+        PushStop::OutOfBounds | PushStop::Immovable(..) | PushStop::NoForce(..) => PushResult::NotMoved,
+        PushStop::Trash(tx, ty) if tx == x && ty == y => PushResult::Trashed,
+        _ => PushResult::Moved,
+    };
+    PushPreview { chain, result, stop }
+}
+
+// internal helper
+#[inline(always)]
+fn push_failed(grid: &mut Grid, x: isize, y: isize, dir: Direction) -> PushResult {
+    grid.emit(UpdateEvent::PushFailed { x, y, dir });
+    PushResult::NotMoved
+}
+
 /// Pulls the cell in a specific direction. Also pulls all cells behind it.
 // #[inline(never)]
 pub fn pull(grid: &mut Grid, x: isize, y: isize, dir: Direction) {
@@ -166,7 +257,7 @@ pub fn pull(grid: &mut Grid, x: isize, y: isize, dir: Direction) {
     let Vector2 { x: ox, y: oy } = dir.to_vector();
     let mut cx = x + ox;
     let mut cy = y + oy;
-    let mut force = 1;
+    let mut force = 1usize;
 
     // Pull the cells. Doesn't have replacement cells.
     // Works like this:
@@ -179,37 +270,47 @@ pub fn pull(grid: &mut Grid, x: isize, y: isize, dir: Direction) {
     loop {
         let cell = grid.get_mut(cx - ox, cy - oy);
         if let Some(cell) = cell {
-            if cell.id() == MOVER || cell.id() == PULLER || cell.id() == PULLSHER || cell.id() == TRASHMOVER || cell.id() == SPEED || cell.id() == MOVLER {
-                if cell.direction() == dir {
-                    cell.set_updated(true);
-                    force += 1;
-                }
-                else if cell.direction() == opposite_dir {
-                    force -= 1;
-                }
+            let cell_force = force_of(cell, dir);
+            if cell_force > 0 {
+                cell.set_updated(true);
             }
+            force = force.wrapping_add_signed(cell_force);
 
             if is_trash(cell, opposite_dir) || force == 0 || !can_move(cell, dir, MoveForce::Pull) {
                 break;
             }
 
+            let id = cell.id();
             let mut do_move = true;
             let old_cell = grid.get(cx, cy);
             if let Some(cell) = old_cell {
-                if cell.id() == ENEMY {
+                if is_enemy(cell) {
                     // cell is deleted and enemy destroyed
+                    grid.emit(UpdateEvent::EnemyKilled { x: cx, y: cy, id });
                     grid.delete(cx, cy);
+                    grid.touch(cx, cy);
+                    if let Some(motion) = grid.motion_mut(cx, cy) {
+                        *motion = CellMotion::default();
+                    }
                     do_move = false;
                 }
                 else if is_trash(cell, dir) {
                     // cell is trashed
+                    grid.emit(UpdateEvent::Trashed { x: cx, y: cy, id });
                     do_move = false;
                 }
             }
 
             let cell = grid.take(cx - ox, cy - oy).unwrap();
+            grid.touch(cx - ox, cy - oy);
             if do_move {
                 grid.set(cx, cy, cell);
+                grid.touch(cx, cy);
+                grid.move_motion(cx - ox, cy - oy, cx, cy);
+                redirect(grid, cx, cy);
+            }
+            else if let Some(motion) = grid.motion_mut(cx - ox, cy - oy) {
+                *motion = CellMotion::default();
             }
 
             cx -= ox;
@@ -221,15 +322,29 @@ pub fn pull(grid: &mut Grid, x: isize, y: isize, dir: Direction) {
     }
 }
 
+/// Sets the direction of a moved cell to the one of a redirector next to it.
+#[inline(always)]
+fn redirect(grid: &mut Grid, x: isize, y: isize) {
+    if !grid.has_redirectors { return; }
+    if let Some(cell) = grid.get(x, y) {
+        if cell.id() == REDIRECTOR { return; }
+    }
+
+    for side in [Direction::Right, Direction::Down, Direction::Left, Direction::Up] {
+        let Vector2 { x: ox, y: oy } = side.to_vector();
+        if let Some(redirector) = grid.get(x + ox, y + oy) {
+            if redirector.id() == REDIRECTOR {
+                rotate_to(grid, x, y, redirector.direction(), side);
+                return;
+            }
+        }
+    }
+}
+
 /// Checks if a cell can be rotated from a specific direction.
 #[inline]
 pub fn can_rotate(cell: &Cell, side: Direction) -> bool {
-    match cell.id() {
-        WALL | GHOST => false,
-        ORIENTATOR => false,
-        SLIDE_WALL if (cell.direction() - side).shrink(2) == Direction::Down => false,
-        _ => true,
-    }
+    cell_data(cell.id()).behavior.can_rotate(cell, side)
 }
 
 // internal helper
@@ -249,7 +364,17 @@ fn rotate(cell: &mut Cell, dir: Direction, side: Direction) -> bool {
 // #[inline(never)]
 pub fn rotate_by(grid: &mut Grid, x: isize, y: isize, dir: Direction, side: Direction) -> bool {
     match grid.get_mut(x, y) {
-        Some(cell) => rotate(cell, cell.direction() + dir, side),
+        Some(cell) => {
+            let rotated = rotate(cell, cell.direction() + dir, side);
+            if rotated && dir != Direction::Right {
+                grid.emit(UpdateEvent::Rotated { x, y, id: cell.id() });
+                grid.touch(x, y);
+                if let Some(motion) = grid.motion_mut(x, y) {
+                    motion.rotate(dir);
+                }
+            }
+            rotated
+        },
         None => false,
     }
 }
@@ -259,7 +384,18 @@ pub fn rotate_by(grid: &mut Grid, x: isize, y: isize, dir: Direction, side: Dire
 // #[inline(never)]
 pub fn rotate_to(grid: &mut Grid, x: isize, y: isize, dir: Direction, side: Direction) -> bool {
     match grid.get_mut(x, y) {
-        Some(cell) => rotate(cell, dir, side),
+        Some(cell) => {
+            let old_dir = cell.direction();
+            let rotated = rotate(cell, dir, side);
+            if rotated && old_dir != dir {
+                grid.emit(UpdateEvent::Rotated { x, y, id: cell.id() });
+                grid.touch(x, y);
+                if let Some(motion) = grid.motion_mut(x, y) {
+                    motion.rotate(dir - old_dir);
+                }
+            }
+            rotated
+        },
         None => false,
     }
 }
@@ -5,3 +5,18 @@ pub mod manipulation;
 pub mod update;
 pub mod codes;
 pub mod cell_data;
+pub mod events;
+pub mod stats;
+pub mod headless;
+pub mod behavior;
+pub mod mods;
+pub mod puzzle;
+pub mod levels;
+pub mod blueprints;
+pub mod find;
+pub mod symmetry;
+pub mod activity;
+pub mod export;
+pub mod recording;
+pub mod lod;
+pub mod settings;
@@ -0,0 +1,234 @@
+//! Custom cells defined by scripts in the `mods` folder.
+//!
+//! Every `*.rhai` file in the folder defines one cell type. The script has to
+//! set the constants `NAME`, `DESCRIPTION`, `SIDES` and `TEXTURE` (file name in
+//! `assets/cells/` without the extension, levels save the cell by it so it has to
+//! be unique) and can define these functions:
+//!
+//! - `can_move(cell_dir, dir, force)`, force is `"push"`, `"pull"` or `"swap"`
+//! - `is_trash(cell_dir, dir)`
+//! - `is_enemy(cell_dir)`
+//! - `can_rotate(cell_dir, side)`
+//! - `can_generate(cell_dir)`
+//! - `force(cell_dir, dir)`, the force the cell adds to pushes and pulls
+//! - `update(grid, x, y, dir)`, called once per tick for every cell of the type
+//!
+//! Directions are numbers: 0 is right, 1 down, 2 left and 3 up.
+//! Inside `update` the grid can be used with `grid.id(x, y)`, `grid.dir(x, y)`,
+//! `grid.push(x, y, dir)`, `grid.pull(x, y, dir)`, `grid.rotate_by(x, y, amount, side)`
+//! and `grid.rotate_to(x, y, dir, side)`. `cell_id(texture)` returns the id of a cell type.
+
+use std::{fs, path::Path, sync::{atomic::{AtomicU8, Ordering}, OnceLock}};
+
+use rhai::{Engine, AST, Scope, CallFnOptions, FuncArgs, Dynamic, module_resolvers::DummyModuleResolver};
+
+use super::{cells::{Cell, CellType, Grid}, direction::Direction, behavior::CellBehavior, cell_data::{CellData, CELL_DATA, register_cells, all_cells}, manipulation::{push, pull, rotate_by, rotate_to, MoveForce}, update::{Subtick, update_each_dir}};
+
+/// Maximum amount of operations a single script call may run.
+const MAX_OPERATIONS: u64 = 100_000;
+
+/// A cell type defined by a script.
+pub struct ScriptCell {
+    name: String,
+    engine: &'static Engine,
+    ast: AST,
+    functions: Vec<String>,
+    id: AtomicU8,
+    subtick: OnceLock<&'static Subtick>,
+}
+
+impl ScriptCell {
+    fn has(&self, function: &str) -> bool {
+        self.functions.iter().any(|f| f == function)
+    }
+
+    /// Calls a function of the script. Errors are printed and turned into `None`.
+    fn call<T: Clone + 'static>(&self, function: &str, args: impl FuncArgs) -> Option<T> {
+        let options = CallFnOptions::new().eval_ast(false);
+        match self.engine.call_fn_with_options::<Dynamic>(options, &mut Scope::new(), &self.ast, function, args) {
+            Ok(value) => value.try_cast(),
+            Err(err) => {
+                eprintln!("{}: error in {function}: {err}", self.name);
+                None
+            },
+        }
+    }
+
+    fn update(&self, grid: &mut Grid) {
+        let id = self.id.load(Ordering::Relaxed);
+        update_each_dir(grid, id, |grid, x, y, dir| {
+            self.call::<()>("update", (ScriptGrid(grid), x as i64, y as i64, dir as i64));
+        });
+    }
+}
+
+impl CellBehavior for ScriptCell {
+    fn can_move(&self, cell: &Cell, direction: Direction, force: MoveForce) -> bool {
+        if !self.has("can_move") { return true; }
+        let force = match force {
+            MoveForce::Push => "push",
+            MoveForce::Pull => "pull",
+            MoveForce::Swap => "swap",
+        };
+        self.call("can_move", (cell.direction() as i64, direction as i64, force)).unwrap_or(true)
+    }
+
+    fn is_trash(&self, cell: &Cell, direction: Direction) -> bool {
+        if !self.has("is_trash") { return false; }
+        self.call("is_trash", (cell.direction() as i64, direction as i64)).unwrap_or(false)
+    }
+
+    fn is_enemy(&self, cell: &Cell) -> bool {
+        if !self.has("is_enemy") { return false; }
+        self.call("is_enemy", (cell.direction() as i64,)).unwrap_or(false)
+    }
+
+    fn can_rotate(&self, cell: &Cell, side: Direction) -> bool {
+        if !self.has("can_rotate") { return true; }
+        self.call("can_rotate", (cell.direction() as i64, side as i64)).unwrap_or(true)
+    }
+
+    fn can_generate(&self, cell: &Cell) -> bool {
+        if !self.has("can_generate") { return true; }
+        self.call("can_generate", (cell.direction() as i64,)).unwrap_or(true)
+    }
+
+    fn force(&self, cell: &Cell, direction: Direction) -> isize {
+        if !self.has("force") { return 0; }
+        self.call::<i64>("force", (cell.direction() as i64, direction as i64)).unwrap_or(0) as isize
+    }
+
+    fn subtick(&self) -> Option<&'static Subtick> {
+        self.subtick.get().copied()
+    }
+}
+
+/// Handle to the grid passed to `update`.
+/// Only valid while the function is running.
+#[derive(Clone)]
+struct ScriptGrid(*mut Grid);
+
+// SAFETY: the handle never outlives the update call it was created for.
+unsafe impl Send for ScriptGrid {}
+unsafe impl Sync for ScriptGrid {}
+
+impl ScriptGrid {
+    fn grid(&mut self) -> &mut Grid {
+        unsafe { &mut *self.0 }
+    }
+}
+
+fn create_engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    // scripts can't load other files
+    engine.set_module_resolver(DummyModuleResolver::new());
+
+    engine.register_type_with_name::<ScriptGrid>("Grid");
+    engine.register_fn("id", |g: &mut ScriptGrid, x: i64, y: i64| {
+        g.grid().get(x as isize, y as isize).as_ref().map_or(0, |cell| cell.id() as i64)
+    });
+    engine.register_fn("dir", |g: &mut ScriptGrid, x: i64, y: i64| {
+        g.grid().get(x as isize, y as isize).as_ref().map_or(0, |cell| cell.direction() as i64)
+    });
+    engine.register_fn("push", |g: &mut ScriptGrid, x: i64, y: i64, dir: i64| {
+        push(g.grid(), x as isize, y as isize, Direction::from(dir as i32), 1, None, false).did_move()
+    });
+    engine.register_fn("pull", |g: &mut ScriptGrid, x: i64, y: i64, dir: i64| {
+        pull(g.grid(), x as isize, y as isize, Direction::from(dir as i32));
+    });
+    engine.register_fn("rotate_by", |g: &mut ScriptGrid, x: i64, y: i64, amount: i64, side: i64| {
+        rotate_by(g.grid(), x as isize, y as isize, Direction::from(amount as i32), Direction::from(side as i32))
+    });
+    engine.register_fn("rotate_to", |g: &mut ScriptGrid, x: i64, y: i64, dir: i64, side: i64| {
+        rotate_to(g.grid(), x as isize, y as isize, Direction::from(dir as i32), Direction::from(side as i32))
+    });
+    engine.register_fn("cell_id", |texture: &str| {
+        all_cells().find(|cell| cell.texture_name == texture).map_or(0, |cell| cell.id as i64)
+    });
+
+    engine
+}
+
+/// Loads all scripts from the `mods` folder and registers their cells.
+/// Scripts that fail to load are skipped with an error message.
+///
+/// Has to be called before the cells are used.
+pub fn load_mods(resource_path: &Path) {
+    let Ok(entries) = fs::read_dir(resource_path.join("mods")) else { return };
+    let mut paths = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "rhai"))
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    let engine: &'static Engine = Box::leak(Box::new(create_engine()));
+    let mut cells = Vec::new();
+    let mut data = Vec::new();
+    for path in paths {
+        if CELL_DATA.len() + data.len() >= CellType::MAX as usize {
+            eprintln!("{}: too many cell types", path.display());
+            continue;
+        }
+        match load_mod(engine, resource_path, &path) {
+            // levels save mod cells by texture, so it has to identify the cell
+            Ok((_, cell_data)) if CELL_DATA.iter().chain(&data).any(|cell| cell.texture_name == cell_data.texture_name) => {
+                eprintln!("{}: texture {} is already used by another cell", path.display(), cell_data.texture_name);
+            },
+            Ok((cell, cell_data)) => {
+                cells.push(cell);
+                data.push(cell_data);
+            },
+            Err(err) => eprintln!("{}: {err}", path.display()),
+        }
+    }
+
+    for (cell, data) in cells.into_iter().zip(register_cells(data)) {
+        cell.id.store(data.id, Ordering::Relaxed);
+    }
+}
+
+fn load_mod(engine: &'static Engine, resource_path: &Path, path: &Path) -> Result<(&'static ScriptCell, CellData), String> {
+    let ast = engine.compile_file(path.to_path_buf()).map_err(|e| e.to_string())?;
+    let mut scope = Scope::new();
+    engine.run_ast_with_scope(&mut scope, &ast).map_err(|e| e.to_string())?;
+
+    let name = scope.get_value::<String>("NAME").ok_or("missing NAME")?;
+    let description = scope.get_value::<String>("DESCRIPTION").unwrap_or_default();
+    let sides = match scope.get_value::<i64>("SIDES").unwrap_or(4) {
+        sides @ (1 | 2 | 4) => sides as usize,
+        _ => return Err("SIDES has to be 1, 2 or 4".to_string()),
+    };
+    let texture = scope.get_value::<String>("TEXTURE").ok_or("missing TEXTURE")?;
+    if texture.contains([';', ',', '=', ':']) {
+        return Err("TEXTURE can't contain ';', ',', '=' or ':'".to_string());
+    }
+    if !resource_path.join(format!("assets/cells/{texture}.png")).is_file() {
+        return Err(format!("texture assets/cells/{texture}.png not found"));
+    }
+
+    let functions = ast.iter_functions().map(|f| f.name.to_string()).collect();
+    let cell: &'static ScriptCell = Box::leak(Box::new(ScriptCell {
+        name: name.clone(),
+        engine,
+        ast,
+        functions,
+        id: AtomicU8::new(0),
+        subtick: OnceLock::new(),
+    }));
+    if cell.has("update") {
+        let _ = cell.subtick.set(Box::leak(Box::new(Subtick {
+            name: Box::leak(format!("mod_{texture}").into_boxed_str()),
+            run: Box::leak(Box::new(|grid: &mut Grid| cell.update(grid))),
+        })));
+    }
+
+    Ok((cell, CellData {
+        id: 0,
+        name: Box::leak(name.into_boxed_str()),
+        description: Box::leak(description.into_boxed_str()),
+        sides,
+        texture_name: Box::leak(texture.into_boxed_str()),
+        behavior: cell,
+    }))
+}
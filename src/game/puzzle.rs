@@ -0,0 +1,148 @@
+use std::collections::BTreeMap;
+
+use super::{cells::{CellType, Grid}, manipulation::is_enemy};
+
+/// What has to be done to solve a puzzle.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Goal {
+    /// All enemies have to be destroyed.
+    DestroyEnemies,
+}
+
+impl Goal {
+    /// Short identifier used in level codes.
+    pub fn code(self) -> &'static str {
+        match self {
+            Goal::DestroyEnemies => "enemies",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Goal> {
+        match code {
+            "enemies" => Some(Goal::DestroyEnemies),
+            _ => None,
+        }
+    }
+
+    /// Human readable description of the goal.
+    pub fn description(self) -> &'static str {
+        match self {
+            Goal::DestroyEnemies => "Destroy all enemies",
+        }
+    }
+
+    /// Checks if the goal is reached on the grid.
+    /// Only meaningful if the level has no `problem`, otherwise it might be reached without doing anything.
+    pub fn is_reached(self, grid: &Grid) -> bool {
+        match self {
+            Goal::DestroyEnemies => !has_enemies(grid),
+        }
+    }
+
+    /// Checks if the goal can be reached from the grid at the start of the level.
+    /// Returns why not if it can't be.
+    pub fn problem(self, grid: &Grid) -> Option<&'static str> {
+        match self {
+            Goal::DestroyEnemies => (!has_enemies(grid)).then_some("there are no enemies to destroy"),
+        }
+    }
+}
+
+// internal helper
+fn has_enemies(grid: &Grid) -> bool {
+    let mut found = false;
+    grid.for_each(|_, _, cell| {
+        if cell.is_some_and(is_enemy) {
+            found = true;
+        }
+    });
+    found
+}
+
+/// Restrictions and goal of a puzzle level.
+///
+/// Outside of the puzzle editor cells can only be placed and removed inside the build zone,
+/// and every placed cell has to be taken from the inventory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Puzzle {
+    width: usize,
+    /// Whether the player can edit a position, indexed like the cells of the grid.
+    zone: Vec<bool>,
+    /// Amount of cells of every type the player can place.
+    pub inventory: BTreeMap<CellType, usize>,
+    pub goal: Goal,
+}
+
+impl Puzzle {
+    /// Creates a puzzle with an empty build zone and inventory.
+    pub fn new(width: usize, height: usize) -> Self {
+        Puzzle {
+            width,
+            zone: vec![false; width * height],
+            inventory: BTreeMap::new(),
+            goal: Goal::DestroyEnemies,
+        }
+    }
+
+    /// Checks if the player can edit the position.
+    #[inline]
+    pub fn in_zone(&self, x: isize, y: isize) -> bool {
+        x >= 0 && y >= 0 && (x as usize) < self.width
+            && self.zone.get(y as usize * self.width + x as usize).copied().unwrap_or(false)
+    }
+
+    pub fn set_zone(&mut self, x: isize, y: isize, value: bool) {
+        if x >= 0 && y >= 0 && (x as usize) < self.width {
+            if let Some(zone) = self.zone.get_mut(y as usize * self.width + x as usize) {
+                *zone = value;
+            }
+        }
+    }
+
+    /// The build zone as a list of alternating run lengths, starting with positions outside the zone.
+    pub fn zone_runs(&self) -> Vec<usize> {
+        let mut runs = vec![0];
+        let mut current = false;
+        for &value in &self.zone {
+            if value != current {
+                runs.push(0);
+                current = value;
+            }
+            *runs.last_mut().unwrap() += 1;
+        }
+        runs
+    }
+
+    /// Sets the build zone from run lengths, see `Puzzle::zone_runs`.
+    pub fn set_zone_runs(&mut self, runs: &[usize]) -> Result<(), &'static str> {
+        let mut index = 0;
+        for (i, &run) in runs.iter().enumerate() {
+            let end = index + run;
+            if end > self.zone.len() { return Err("zone too large"); }
+            self.zone[index..end].fill(i % 2 == 1);
+            index = end;
+        }
+        Ok(())
+    }
+
+    /// Amount of cells of the type left in the inventory.
+    pub fn available(&self, id: CellType) -> usize {
+        self.inventory.get(&id).copied().unwrap_or(0)
+    }
+
+    /// Removes a cell from the inventory. Returns false if there is none left.
+    pub fn take(&mut self, id: CellType) -> bool {
+        match self.inventory.get_mut(&id) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                true
+            },
+            _ => false,
+        }
+    }
+
+    /// Puts a cell back into the inventory.
+    pub fn put_back(&mut self, id: CellType) {
+        *self.inventory.entry(id).or_insert(0) += 1;
+    }
+}
@@ -0,0 +1,127 @@
+use std::{fs::File, io::BufWriter, path::Path};
+
+use image::{codecs::gif::{GifEncoder, Repeat}, Delay, Frame};
+
+use super::{cells::Grid, export::{clamp_region, image_size, render_grid, CellTextures, Region}};
+
+/// Frames after which a recording stops capturing, so it doesn't use up all memory.
+pub const MAX_RECORDED_FRAMES: usize = 1000;
+
+/// Speed of reducing GIF frames with more than 256 colors, from 1 (best colors, slowest) to 30 (fastest).
+/// Frames with fewer colors are encoded as they are.
+const GIF_SPEED: i32 = 10;
+
+/// Copy of a grid without the recorded data, as kept for a frame.
+pub fn frame(grid: &Grid) -> Grid {
+    let mut frame = grid.clone();
+    frame.events = None;
+    frame.profile = None;
+    frame.motion = None;
+    frame.activity = None;
+    frame.frames = None;
+    frame
+}
+
+/// How a simulation is recorded.
+#[derive(Debug, Clone, Copy)]
+pub struct RecordOptions {
+    /// Ticks skipped between two frames.
+    pub frame_skip: u32,
+    /// Pixels per cell.
+    pub cell_size: u32,
+    /// Time every frame is shown in milliseconds.
+    pub frame_time: u32,
+    /// Part of the grid that is recorded, the whole grid if not set.
+    pub region: Option<Region>,
+}
+
+impl Default for RecordOptions {
+    fn default() -> Self {
+        RecordOptions {
+            frame_skip: 0,
+            cell_size: 8,
+            frame_time: 100,
+            region: None,
+        }
+    }
+}
+
+/// The grid states of a recorded simulation.
+/// The frames are only rendered when the recording is saved.
+#[derive(Debug, Clone)]
+pub struct Recording {
+    pub options: RecordOptions,
+    frames: Vec<Grid>,
+    /// Tick of the last frame.
+    last_tick: Option<u32>,
+}
+
+impl Recording {
+    pub fn new(options: RecordOptions) -> Self {
+        Recording { options, frames: Vec::new(), last_tick: None }
+    }
+
+    /// Adds the grid as a frame if enough ticks passed since the last one.
+    /// The recording starts over if the grid was reset.
+    pub fn capture(&mut self, grid: &Grid) {
+        if let Some(last) = self.last_tick {
+            if grid.tick_count < last {
+                self.frames.clear();
+            }
+            else if grid.tick_count <= last + self.options.frame_skip {
+                return;
+            }
+        }
+        if self.frames.len() >= MAX_RECORDED_FRAMES { return; }
+
+        self.frames.push(frame(grid));
+        self.last_tick = Some(grid.tick_count);
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Renders the frames and saves them as an animated PNG if the file ends with `.png`, as a GIF otherwise.
+    /// Every frame is rendered and compressed, long recordings of large regions take a while.
+    pub fn save(&self, textures: &CellTextures, path: &Path) -> Result<(), String> {
+        if self.frames.is_empty() {
+            return Err("nothing recorded".to_string());
+        }
+
+        let RecordOptions { cell_size, frame_time, region, .. } = self.options;
+        let region = clamp_region(&self.frames[0], region)?;
+        image_size(region, cell_size)?;
+        let region = Some(region);
+        let textures = textures.scaled(cell_size);
+        let file = BufWriter::new(File::create(path).map_err(|e| format!("couldn't create file: {e}"))?);
+        let images = self.frames.iter().map(|frame| render_grid(frame, &textures, region, cell_size));
+
+        if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("png")) {
+            let first = render_grid(&self.frames[0], &textures, region, cell_size)?;
+            let mut encoder = png::Encoder::new(file, first.width(), first.height());
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            encoder.set_animated(self.frames.len() as u32, 0).map_err(|e| e.to_string())?;
+            encoder.set_frame_delay(frame_time.min(u16::MAX as u32) as u16, 1000).map_err(|e| e.to_string())?;
+            let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+            for image in images {
+                let image = image?;
+                if image.dimensions() != first.dimensions() {
+                    return Err("the grid was resized during the recording".to_string());
+                }
+                writer.write_image_data(image.as_raw()).map_err(|e| e.to_string())?;
+            }
+            writer.finish().map_err(|e| e.to_string())
+        }
+        else {
+            let mut encoder = GifEncoder::new_with_speed(file, GIF_SPEED);
+            encoder.set_repeat(Repeat::Infinite).map_err(|e| e.to_string())?;
+            for image in images {
+                let image = image?;
+                encoder.encode_frame(Frame::from_parts(image, 0, 0, Delay::from_numer_denom_ms(frame_time, 1))).map_err(|e| e.to_string())?;
+            }
+            Ok(())
+        }
+    }
+}
@@ -1,11 +1,11 @@
 extern crate clipboard;
 
-use std::{time::Instant, collections::{HashMap, HashSet}, rc::Rc, path::PathBuf};
+use std::{time::{Instant, SystemTime, UNIX_EPOCH}, collections::{HashMap, HashSet, BTreeMap, VecDeque}, rc::Rc, path::PathBuf, thread, mem};
 use clipboard::{ClipboardContext, ClipboardProvider};
-use image::{imageops::{rotate90, rotate180, rotate270}, ImageBuffer, Rgba};
+use image::Rgba;
 use speedy2d::{window::{WindowHandler, WindowHelper, VirtualKeyCode, KeyScancode, MouseButton, MouseScrollDistance}, Graphics2D, color::Color, image::{ImageDataType, ImageFileFormat, ImageSmoothingMode, ImageHandle}, dimen::Vector2, shape::Rectangle, font::{Font, TextLayout, TextOptions, FormattedTextBlock, TextAlignment}};
 
-use crate::game::{cells::{DEFAULT_GRID_HEIGHT, DEFAULT_GRID_WIDTH, CellType, Cell, Grid}, direction::Direction, update::{update, run_update_loop}, codes::{import, export_q1, export_q2}, cell_data::{CELL_DATA, HOTBAR_ITEMS}};
+use crate::game::{manipulation::{MoveForce, PushPreview, PushResult, PushStop, push_preview, can_generate}, cells::{DEFAULT_GRID_HEIGHT, DEFAULT_GRID_WIDTH, BOOKMARK_COUNT, CellType, Cell, Grid, Bookmark}, direction::Direction, update::{update, run_update_loop, UpdateOrder, TickStepper}, codes::{import, export_q1, export_q2}, cell_data::{CellData, cell_data, hotbar_items, all_cells, MOVER, PULLSHER, TRASHMOVER, SPEED, GENERATOR}, stats::{Statistics, HISTORY_LENGTH}, puzzle::Puzzle, levels::{LevelPack, render_minimap, render_thumbnail, average_color}, blueprints::{BlueprintLibrary, copy_region, rotate_grid}, find::{CellQuery, Replacement}, symmetry::{Symmetry, grid_center}, activity::{Activity, ACTIVITY_WINDOW}, export::{create_rotated_textures, export_png, CellTextures}, recording::{Recording, RecordOptions}, lod::{ChunkCache, ColorCache, CHUNK_SIZE}, settings::{export_dir, Action, Settings}};
 
 use super::update::UpdateState;
 
@@ -30,10 +30,61 @@ const TOOLTIP_WIDTH: f32 = 400.0;
 const TOOLTIP_HEIGHT: f32 = 200.0;
 const TOOLTIP_PADDING: f32 = 20.0;
 
-#[cfg(target_os = "macos")]
-const COMMAND_KEY: VirtualKeyCode = VirtualKeyCode::LWin;
-#[cfg(not(target_os = "macos"))]
-const COMMAND_KEY: VirtualKeyCode = VirtualKeyCode::LControl;
+const STATS_WIDTH: f32 = 300.0;
+const STATS_PADDING: f32 = 10.0;
+const STATS_GRAPH_HEIGHT: f32 = 100.0;
+
+const PROFILE_BAR_WIDTH: f32 = 250.0;
+
+const LEVEL_THUMBNAIL_SIZE: f32 = 128.0;
+const LEVEL_SPACING: f32 = 40.0;
+const LEVEL_TOP: f32 = 80.0;
+
+const BLUEPRINT_PANEL_WIDTH: f32 = 300.0;
+const BLUEPRINT_ROW_HEIGHT: f32 = 50.0;
+
+const FIND_PANEL_WIDTH: f32 = 320.0;
+const FIND_ROW_HEIGHT: f32 = 36.0;
+const FIND_ROWS: usize = 6;
+
+const RECORD_PANEL_WIDTH: f32 = 240.0;
+const RECORD_ROWS: usize = 3;
+/// Highest frame skip and cell size selectable in the recording panel.
+const MAX_RECORD_FRAME_SKIP: u32 = 100;
+const MAX_RECORD_CELL_SIZE: u32 = 64;
+
+const INSPECTOR_WIDTH: f32 = 380.0;
+const INSPECTOR_PADDING: f32 = 10.0;
+const INSPECTOR_LINE_HEIGHT: f32 = 22.0;
+
+/// Below this many pixels per cell, cells are drawn as solid colors.
+const LOD_COLOR_PIXELS: f32 = 4.0;
+/// Below this many pixels per cell, the grid is drawn in chunks.
+const LOD_CHUNK_PIXELS: f32 = 16.0;
+/// Pixels per cell of the chunk textures.
+const LOD_CHUNK_CELL_SIZE: u32 = 8;
+
+/// Size of the longer side of the minimap, larger grids are sampled down to this many pixels.
+const MINIMAP_SIZE: f32 = 160.0;
+const MINIMAP_MARGIN: f32 = 10.0;
+/// Seconds at least between redrawing the minimap.
+const MINIMAP_REFRESH: f32 = 0.1;
+
+/// Grids with more cells than this are not animated, recording the motion would slow them down.
+const SMOOTH_MAX_CELLS: usize = 250_000;
+/// Longest time a tick is animated in seconds, also used for single steps.
+const SMOOTH_MAX_TIME: f32 = 0.15;
+
+/// Pixels per cell of exported images.
+const EXPORT_CELL_SIZE: u32 = 32;
+
+/// Amount of tools in the tool menu.
+const TOOL_COUNT: usize = 11;
+/// Amount of tools in one column of the tool menu.
+const TOOLS_PER_COLUMN: usize = 5;
+
+/// Scrolled pixels that zoom as much as one scroll step.
+const PIXELS_PER_ZOOM_STEP: f32 = 20.0;
 
 type Text = Rc<FormattedTextBlock>;
 
@@ -42,26 +93,47 @@ enum Tool {
     Place,
     Rect(isize),
     Circle(isize),
+    /// Replaces the connected area of the cell under the mouse.
+    Fill,
+    /// Places cells along a line, optionally rotating them along the line.
+    Line(bool),
+    /// Rectangle spanned by dragging the mouse, filled or only the outline.
+    DragRect(bool),
+    /// Ellipse spanned by dragging the mouse, filled or only the outline.
+    DragEllipse(bool),
+    /// Selects a region to save as a blueprint.
+    Select,
 }
 
 pub struct WinHandler {
     resource_path: PathBuf,
+    settings: Settings,
     assets: Option<Assets>,
     prev_time: Instant,
     keys: HashSet<VirtualKeyCode>,
     mouse: Option<MouseButton>,
     mouse_pos: Vector2<f32>,
     undo_stack: CellUndoStack,
+    /// Index of the symmetry image that placed each position during the current stroke.
+    stroke: HashMap<(isize, isize), usize>,
 
     help_text: Option<Text>,
     hotbar_item_text: Option<HashMap<CellType, Tooltip>>,
 
+    hotbar: Vec<&'static [CellData]>,
     active_item: usize,
     hotbar_state: Vec<usize>,
     open_item_menu: Option<usize>,
     direction: Direction,
     place: bool,
     placement_tool: Tool,
+    /// Position where a line or shape tool was pressed.
+    drag_start: Option<(isize, isize)>,
+    /// Selected region as the lowest and highest corner.
+    selection: Option<((isize, isize), (isize, isize))>,
+    symmetry: Symmetry,
+    /// Center of the symmetry in half cells, the grid center if not set.
+    symmetry_center: Option<(isize, isize)>,
 
     check_loop: bool,
     loop_length: u32,
@@ -69,14 +141,83 @@ pub struct WinHandler {
     running: bool,
     running_state: Option<UpdateState>,
     show_help: bool,
+    show_stats: bool,
+    stats: Statistics,
+    show_profile: bool,
+    show_inspector: bool,
+    show_heatmap: bool,
+    subtick_mode: bool,
+    /// Tick being run one subtick at a time in the subtick mode.
+    stepper: Option<TickStepper>,
+    /// Name of the last subtick run in the subtick mode and the progress of the tick.
+    last_subtick: Option<(&'static str, (usize, usize))>,
+    /// Positions changed by the last subtick.
+    subtick_changes: Vec<(isize, isize)>,
     tick_times: [f32; 10],
+    /// Whether moving cells are animated between ticks.
+    smooth: bool,
+    /// Tick count of the shown grid, when it was first shown and how long it is animated in seconds.
+    shown_tick: (u32, Instant, f32),
     is_initial: bool,
     threaded: bool,
+    /// Whether puzzle restrictions are lifted to edit the puzzle itself.
+    puzzle_edit: bool,
+    solved: bool,
+
+    levels: LevelPack,
+    /// Index of the loaded level in the level pack.
+    current_level: Option<usize>,
+    show_levels: bool,
+    level_scroll: f32,
+
+    blueprints: BlueprintLibrary,
+    blueprint_thumbnails: HashMap<String, ImageHandle>,
+    /// Blueprint placed instead of the active cell.
+    active_blueprint: Option<usize>,
+    show_blueprints: bool,
+    blueprint_search: String,
+    /// Name typed in for a new blueprint.
+    blueprint_name: Option<String>,
+    /// Set when a key opens a text field, so its character isn't typed into it.
+    ignore_char: bool,
+
+    /// Find and replace dialog, if it is open.
+    find: Option<(CellQuery, Replacement)>,
+    /// Amount of cells the find query matched, with the query and `grid_version` it was counted for.
+    find_count: Option<(CellQuery, (u32, u32), usize)>,
+    /// Counts changes of the grid that don't finish a tick, like edits, see `grid_version`.
+    grid_changes: u32,
+
+    /// Simulation being recorded to an animation.
+    recording: Option<Recording>,
+    /// Frame skip and cell size of recordings, changed in the recording panel.
+    record_options: RecordOptions,
+
+    /// Minimap image with the grid version it shows and when it was drawn.
+    minimap: Option<(ImageHandle, (u32, u32), Instant)>,
+    /// Set while the mouse is dragged on the minimap.
+    minimap_drag: bool,
+    /// Last mouse position while panning with the middle mouse button.
+    pan_from: Option<Vector2<f32>>,
+    /// Textures of the grid when zoomed out.
+    chunks: ChunkCache,
+    /// Texture of the visible cells when they are drawn as solid colors.
+    color_cache: ColorCache,
 }
 
 impl WinHandler {
     #[inline(always)]
     pub fn new(resource_path: PathBuf) -> Self {
+        let hotbar = hotbar_items();
+        let levels = LevelPack::load(&resource_path);
+        let blueprints = BlueprintLibrary::load();
+        let settings = Settings::load();
+        unsafe {
+            grid = Grid::new(settings.grid_width, settings.grid_height);
+            initial = grid.clone();
+            screen_x = settings.grid_width as f32 / 2.0;
+            screen_y = settings.grid_height as f32 / 2.0;
+        }
         WinHandler {
             resource_path,
             assets: None,
@@ -85,26 +226,315 @@ impl WinHandler {
             mouse: None,
             mouse_pos: Vector2::new(0.0, 0.0),
             undo_stack: CellUndoStack::new(),
+            stroke: HashMap::new(),
 
             help_text: None,
             hotbar_item_text: None,
 
+            hotbar_state: vec![0; hotbar.len()],
+            hotbar,
             active_item: 0,
-            hotbar_state: vec![0; HOTBAR_ITEMS.len()],
             open_item_menu: None,
             direction: Direction::Right,
             place: true,
             placement_tool: Tool::Place,
+            drag_start: None,
+            selection: None,
+            symmetry: Symmetry::None,
+            symmetry_center: None,
 
-            check_loop: false,
+            check_loop: settings.check_loop,
             loop_length: 0,
 
             running: false,
             running_state: None,
             show_help: true,
+            show_stats: false,
+            stats: Statistics::new(),
+            show_profile: false,
+            show_inspector: false,
+            show_heatmap: false,
+            subtick_mode: false,
+            stepper: None,
+            last_subtick: None,
+            subtick_changes: Vec::new(),
             tick_times: [0.0; 10],
+            smooth: true,
+            shown_tick: (0, Instant::now(), 0.0),
             is_initial: true,
-            threaded: false,
+            threaded: settings.threaded,
+            puzzle_edit: false,
+            solved: false,
+
+            levels,
+            current_level: None,
+            show_levels: false,
+            level_scroll: 0.0,
+
+            blueprints,
+            blueprint_thumbnails: HashMap::new(),
+            active_blueprint: None,
+            show_blueprints: false,
+            blueprint_search: String::new(),
+            blueprint_name: None,
+            ignore_char: false,
+
+            find: None,
+            find_count: None,
+            grid_changes: 0,
+
+            recording: None,
+            record_options: RecordOptions::default(),
+
+            minimap: None,
+            minimap_drag: false,
+            pan_from: None,
+            chunks: ChunkCache::default(),
+            color_cache: ColorCache::default(),
+            settings,
+        }
+    }
+}
+
+impl WinHandler {
+    /// Starts or stops recording the data shown by the open overlays on a grid.
+    fn set_recording(&self, target: &mut Grid) {
+        if self.show_stats {
+            target.events.get_or_insert_with(Vec::new);
+        }
+        else {
+            target.events = None;
+        }
+        if self.show_profile {
+            target.profile.get_or_insert_with(Vec::new);
+        }
+        else {
+            target.profile = None;
+        }
+        if self.show_heatmap {
+            target.activity.get_or_insert_with(|| Activity::new(ACTIVITY_WINDOW));
+        }
+        else {
+            target.activity = None;
+        }
+        if self.recording.is_some() {
+            target.frames.get_or_insert_with(Vec::new);
+        }
+        else {
+            target.frames = None;
+        }
+        if self.smooth && target.width * target.height <= SMOOTH_MAX_CELLS {
+            target.motion.get_or_insert_with(Vec::new);
+        }
+        else {
+            target.motion = None;
+        }
+    }
+
+    /// Identifies the state of the grid, for data derived from it that is only updated when it changes.
+    unsafe fn grid_version(&self) -> (u32, u32) {
+        (grid.tick_count, self.grid_changes)
+    }
+
+    /// Replaces the grid, stopping the simulation.
+    fn load_grid(&mut self, new_grid: Grid) {
+        if let Some(state) = self.running_state.take() {
+            state.lock().unwrap().0 = false;
+        }
+        self.running = false;
+        self.is_initial = true;
+        self.loop_length = 0;
+        self.puzzle_edit = false;
+        self.solved = false;
+        self.current_level = None;
+        self.stepper = None;
+        self.last_subtick = None;
+        self.subtick_changes.clear();
+        self.grid_changes += 1;
+        unsafe {
+            grid = new_grid;
+            screen_x = grid.width as f32 / 2.0;
+            screen_y = grid.height as f32 / 2.0;
+            screen_zoom = 1.0;
+        }
+    }
+
+    unsafe fn symmetry_center(&self) -> (isize, isize) {
+        self.symmetry_center.unwrap_or_else(|| grid_center(&grid))
+    }
+
+    /// The selected hotbar cell, pointing in the placement direction.
+    fn active_cell(&self) -> Cell {
+        Cell::new(self.hotbar[self.active_item][self.hotbar_state[self.active_item]].id, self.direction)
+    }
+
+    fn load_level(&mut self, index: usize) {
+        self.load_grid(self.levels.levels[index].grid.clone());
+        self.current_level = Some(index);
+        self.show_levels = false;
+    }
+
+    /// Saves the selected region as a blueprint.
+    fn save_blueprint(&mut self, name: &str) {
+        let name = name.trim().to_string();
+        let Some((min, max)) = self.selection else { return };
+        if name.is_empty() { return; }
+
+        let region = unsafe { copy_region(&grid, min, max) };
+        let thumbnail = render_thumbnail(&region, &self.assets.as_ref().unwrap().cell_colors);
+        self.blueprint_thumbnails.remove(&name);
+        if let Err(err) = self.blueprints.save(name, region, thumbnail) {
+            eprintln!("couldn't save blueprint: {err}");
+        }
+    }
+
+    /// Saves the selection, or the whole grid if nothing is selected, as an image in the export folder.
+    fn export_image(&self) {
+        let Some(folder) = export_dir() else {
+            eprintln!("couldn't export image: no folder to save it in");
+            return;
+        };
+        let path = folder.join(format!("grid-{}.png", unix_time()));
+        let result = std::fs::create_dir_all(&folder).map_err(|e| e.to_string())
+            .and_then(|_| CellTextures::load(&self.resource_path))
+            .and_then(|textures| unsafe { export_png(&grid, &textures, self.selection, EXPORT_CELL_SIZE, &path) });
+        match result {
+            Ok(()) => println!("exported image to {}", path.display()),
+            Err(err) => eprintln!("couldn't export image: {err}"),
+        }
+    }
+
+    /// Starts recording the selection, or the whole grid if nothing is selected.
+    /// Stopping saves the recording as a GIF in the export folder.
+    fn toggle_recording(&mut self) {
+        let Some(recording) = self.recording.take() else {
+            let mut recording = Recording::new(RecordOptions { region: self.selection, ..self.record_options });
+            unsafe { recording.capture(&grid); }
+            self.recording = Some(recording);
+            return;
+        };
+
+        let Some(folder) = export_dir() else {
+            eprintln!("couldn't save recording: no folder to save it in");
+            return;
+        };
+        // rendering and encoding the frames takes a while
+        let resource_path = self.resource_path.clone();
+        thread::spawn(move || {
+            let path = folder.join(format!("recording-{}.gif", unix_time()));
+            let result = std::fs::create_dir_all(&folder).map_err(|e| e.to_string())
+                .and_then(|_| CellTextures::load(&resource_path))
+                .and_then(|textures| recording.save(&textures, &path));
+            match result {
+                Ok(()) => println!("saved recording to {}", path.display()),
+                Err(err) => eprintln!("couldn't save recording: {err}"),
+            }
+        });
+    }
+
+    /// Runs the next subtick, starting a new tick if needed.
+    unsafe fn step_subtick(&mut self) {
+        self.grid_changes += 1;
+        let before = grid.clone();
+        let stepper = self.stepper.get_or_insert_with(|| TickStepper::new(&mut grid));
+        self.last_subtick = stepper.step(&mut grid).map(|subtick| (subtick.name, stepper.progress()));
+        if stepper.is_finished() {
+            self.stepper = None;
+        }
+
+        self.subtick_changes.clear();
+        before.for_each(|x, y, cell| {
+            if cell != grid.get(x, y).as_ref() {
+                self.subtick_changes.push((x, y));
+            }
+        });
+    }
+
+    /// Leaves the subtick mode, running the rest of the current tick.
+    unsafe fn finish_subticks(&mut self) {
+        if let Some(mut stepper) = self.stepper.take() {
+            stepper.finish(&mut grid);
+        }
+        self.last_subtick = None;
+        self.subtick_changes.clear();
+    }
+
+    /// Handles a click on a row of the recording panel, left clicks increase the value and right clicks decrease it.
+    fn record_click(&mut self, row: usize, button: MouseButton) {
+        let Some(recording) = &mut self.recording else { return };
+        let options = &mut recording.options;
+        match (row, button) {
+            (1, MouseButton::Left) => options.frame_skip = (options.frame_skip + 1).min(MAX_RECORD_FRAME_SKIP),
+            (1, MouseButton::Right) => options.frame_skip = options.frame_skip.saturating_sub(1),
+            (2, MouseButton::Left) => options.cell_size = (options.cell_size * 2).min(MAX_RECORD_CELL_SIZE),
+            (2, MouseButton::Right) => options.cell_size = (options.cell_size / 2).max(1),
+            _ => {},
+        }
+        // the next recording starts with the same options
+        self.record_options.frame_skip = options.frame_skip;
+        self.record_options.cell_size = options.cell_size;
+    }
+
+    /// Handles a click on a row of the find and replace dialog.
+    fn find_click(&mut self, row: usize, button: MouseButton) {
+        let active_id = self.active_cell().id();
+        let Some((query, replacement)) = &mut self.find else { return };
+        match (row, replacement) {
+            (0, _) => query.id = active_id,
+            (1, _) => query.direction = next_direction(query.direction),
+            (2, _) => query.region = if query.region.is_some() { None } else { self.selection },
+            (3, replacement) if button == MouseButton::Right => {
+                *replacement = match replacement {
+                    Replacement::Change(..) => Replacement::Delete,
+                    Replacement::Delete => Replacement::Change(None, None),
+                };
+            },
+            (3, Replacement::Change(id, _)) => *id = Some(active_id),
+            (3, replacement) => *replacement = Replacement::Change(Some(active_id), None),
+            (4, Replacement::Change(_, direction)) => *direction = next_direction(*direction),
+            (5, _) => self.replace_all(),
+            _ => {},
+        }
+    }
+
+    /// Replaces all cells matched by the find and replace dialog as one undo step.
+    fn replace_all(&mut self) {
+        let Some((query, replacement)) = self.find else { return };
+        if !replacement.changes(&query) { return; }
+        unsafe {
+            if grid.puzzle.is_some() && !self.puzzle_edit && !self.is_initial { return; }
+
+            self.undo_stack.start(&grid);
+            self.grid_changes += 1;
+            for (x, y) in query.find(&grid) {
+                let cell = grid.get_mut(x, y);
+                let place_cell = cell.as_ref().and_then(|cell| replacement.apply(cell));
+                edit_cell(&mut self.undo_stack, x, y, cell, place_cell, self.puzzle_edit);
+            }
+        }
+    }
+
+    /// Handles keys while a text field is open.
+    fn text_key_down(&mut self, key: VirtualKeyCode) {
+        match key {
+            VirtualKeyCode::Escape if self.blueprint_name.is_some() => self.blueprint_name = None,
+            VirtualKeyCode::Escape => self.show_blueprints = false,
+            VirtualKeyCode::Backspace => {
+                match &mut self.blueprint_name {
+                    Some(name) => name.pop(),
+                    None => self.blueprint_search.pop(),
+                };
+            },
+            VirtualKeyCode::Return => {
+                if let Some(name) = self.blueprint_name.take() {
+                    self.save_blueprint(&name);
+                }
+                else if let Some(&index) = self.blueprints.search(&self.blueprint_search).first() {
+                    self.active_blueprint = Some(index);
+                    self.show_blueprints = false;
+                }
+            },
+            _ => {},
         }
     }
 }
@@ -144,7 +574,7 @@ impl WindowHandler for WinHandler {
 
             unsafe {
                 self.help_text = Some(font.layout_text(
-                    "WASD to move\nR+F to zoom\nLeft click to place\nRight click to delete\nAlt+R/F to change cursor size\nI+O to import/export\nSpace to start\nG to step\nT to reset\n\nPress ESC to hide this message",
+                    &help_text(&self.settings),
                     25.0,
                     TextOptions::new()
                         .with_wrap_to_width(SCREEN_WIDTH, TextAlignment::Center)
@@ -168,7 +598,7 @@ impl WindowHandler for WinHandler {
                 //         ))
                 //     })
                 // }).collect());
-                self.hotbar_item_text = Some(HOTBAR_ITEMS.iter().flat_map(|a| {
+                self.hotbar_item_text = Some(self.hotbar.iter().flat_map(|a| {
                     a.iter().map(|cell_type| {
                         (cell_type.id, Tooltip::new(
                             &font,
@@ -180,17 +610,19 @@ impl WindowHandler for WinHandler {
                 }).collect());
             }
 
+            let mut cell_colors = HashMap::new();
             let assets = Assets {
                 cell_bg: img!("assets/background.png"),
                 cells: {
                     let mut map = HashMap::new();
-                    for cell in CELL_DATA {
+                    for cell in all_cells() {
                         let [
                             tex0,
                             tex1,
                             tex2,
                             tex3,
-                        ] = create_rotated_textures(cell.sides, self.resource_path.join("assets/cells/".to_string() + cell.texture_name + ".png"));
+                        ] = create_rotated_textures(cell.sides, self.resource_path.join("assets/cells/".to_string() + cell.texture_name + ".png")).unwrap();
+                        cell_colors.insert(cell.id, average_color(&tex0));
                         map.insert(cell.id, [
                             g.create_image_from_raw_pixels(ImageDataType::RGBA, ImageSmoothingMode::NearestNeighbor, Vector2::new(tex0.width(), tex0.height()), &tex0.into_raw()).unwrap(),
                             g.create_image_from_raw_pixels(ImageDataType::RGBA, ImageSmoothingMode::NearestNeighbor, Vector2::new(tex1.width(), tex1.height()), &tex1.into_raw()).unwrap(),
@@ -204,8 +636,22 @@ impl WindowHandler for WinHandler {
                 tool_place: img!("assets/tool_place.png"),
                 tool_rect: img!("assets/tool_rect.png"),
                 tool_circle: img!("assets/tool_circle.png"),
+                tool_fill: img!("assets/tool_fill.png"),
+                tool_line: img!("assets/tool_line.png"),
+                tool_line_oriented: img!("assets/tool_line_oriented.png"),
+                tool_drag_rect: img!("assets/tool_drag_rect.png"),
+                tool_drag_rect_filled: img!("assets/tool_drag_rect_filled.png"),
+                tool_drag_ellipse: img!("assets/tool_drag_ellipse.png"),
+                tool_drag_ellipse_filled: img!("assets/tool_drag_ellipse_filled.png"),
+                tool_select: img!("assets/tool_select.png"),
 
                 font,
+                thumbnails: self.levels.levels.iter().map(|level| {
+                    let image = render_thumbnail(&level.grid, &cell_colors);
+                    g.create_image_from_raw_pixels(ImageDataType::RGBA, ImageSmoothingMode::NearestNeighbor, Vector2::new(image.width(), image.height()), &image.into_raw()).unwrap()
+                }).collect(),
+                cell_colors,
+                chunk_textures: CellTextures::load(&self.resource_path).unwrap().scaled(LOD_CHUNK_CELL_SIZE),
             };
 
             self.assets = Some(assets);
@@ -233,125 +679,257 @@ impl WindowHandler for WinHandler {
         }
         if let Some(state) = &self.running_state {
             unsafe {
-                let state = state.lock().unwrap();
+                let mut state = state.lock().unwrap();
+                // the update thread picks the recordings up from the shared grid
+                self.set_recording(&mut state.1);
+                // bookmarks saved while running are kept
+                let bookmarks = grid.bookmarks;
+                let frames = state.1.frames.as_mut().map(mem::take);
                 grid = state.1.clone();
+                grid.bookmarks = bookmarks;
+                // every tick the update thread ran since the last drawn frame
+                if let (Some(recording), Some(frames)) = (&mut self.recording, frames) {
+                    for frame in &frames {
+                        recording.capture(frame);
+                    }
+                }
+                if let Some(events) = &mut state.1.events {
+                    events.clear();
+                }
                 self.tick_times.rotate_left(1);
                 self.tick_times[9] = state.2;
             }
         }
 
+        unsafe {
+            if let Some(puzzle) = &grid.puzzle {
+                if !self.is_initial && !self.solved && puzzle.goal.problem(&initial).is_none() && puzzle.goal.is_reached(&grid) {
+                    self.solved = true;
+                    if let Some(index) = self.current_level {
+                        self.levels.set_solved(index);
+                    }
+                }
+            }
+
+            if self.show_stats {
+                self.stats.collect(&mut grid);
+            }
+            self.set_recording(&mut grid);
+
+            // without the update thread every tick is drawn
+            if let (Some(recording), None) = (&mut self.recording, &self.running_state) {
+                recording.capture(&grid);
+            }
+
+            // only single ticks are animated, the motion is recorded for the last one
+            let (shown, shown_at, _) = self.shown_tick;
+            if grid.tick_count != shown {
+                let duration = if grid.tick_count == shown + 1 && self.running {
+                    shown_at.elapsed().as_secs_f32().min(SMOOTH_MAX_TIME)
+                }
+                else if grid.tick_count == shown + 1 {
+                    SMOOTH_MAX_TIME
+                }
+                else {
+                    0.0
+                };
+                self.shown_tick = (grid.tick_count, Instant::now(), duration);
+            }
+        }
+
         unsafe {
             let hotbar_rect = Rectangle::new(
                 Vector2::new(0.0, SCREEN_HEIGHT - HOTBAR_HEIGHT),
                 Vector2::new(SCREEN_WIDTH, SCREEN_HEIGHT),
             );
-            if self.keys.contains(&VirtualKeyCode::W) { screen_y += delta_secs * CELL_SPEED / screen_zoom; }
-            if self.keys.contains(&VirtualKeyCode::S) { screen_y -= delta_secs * CELL_SPEED / screen_zoom; }
-            if self.keys.contains(&VirtualKeyCode::A) { screen_x -= delta_secs * CELL_SPEED / screen_zoom; }
-            if self.keys.contains(&VirtualKeyCode::D) { screen_x += delta_secs * CELL_SPEED / screen_zoom; }
+            if !self.show_levels {
+                if self.settings.keymap.is_held(Action::MoveUp, &self.keys) { screen_y += delta_secs * CELL_SPEED / screen_zoom; }
+                if self.settings.keymap.is_held(Action::MoveDown, &self.keys) { screen_y -= delta_secs * CELL_SPEED / screen_zoom; }
+                if self.settings.keymap.is_held(Action::MoveLeft, &self.keys) { screen_x -= delta_secs * CELL_SPEED / screen_zoom; }
+                if self.settings.keymap.is_held(Action::MoveRight, &self.keys) { screen_x += delta_secs * CELL_SPEED / screen_zoom; }
+            }
+            if self.minimap_drag {
+                (screen_x, screen_y) = minimap_to_grid(self.mouse_pos);
+            }
 
         // grid
-            draw_grid(assets, g);
+            let (_, shown_at, duration) = self.shown_tick;
+            // the frame is shown after drawing, so the animation is a frame ahead
+            let progress = (shown_at.elapsed().as_secs_f32() + delta_secs) / duration;
+            let progress = if grid.motion.is_some() && !self.subtick_mode && progress < 1.0 { Some(progress) } else { None };
+            draw_grid(assets, g, &mut self.chunks, &mut self.color_cache, self.find.as_ref().map(|(query, _)| query), progress);
+
+            if let Some(activity) = &grid.activity {
+                draw_heatmap(g, activity);
+            }
+
+            for &(x, y) in &self.subtick_changes {
+                g.draw_rectangle(region_rect((x, y), (x, y)), Color::from_hex_argb(0x60ff4000));
+            }
+
+            if let Some((min, max)) = self.selection {
+                draw_stroke_rect(g, region_rect(min, max), Color::WHITE, 2.0);
+            }
+
+            if self.symmetry != Symmetry::None {
+                draw_symmetry_axes(g, self.symmetry, self.symmetry_center());
+            }
+
+        // push preview
+            if !self.running && !self.show_levels && !is_inside(hotbar_rect.clone(), self.mouse_pos) {
+                let (x, y) = mouse_cell(self.mouse_pos);
+                if let Some(cell) = grid.get(x, y) {
+                    let dir = cell.direction();
+                    let offset = dir.to_vector();
+                    let preview = match cell.id() {
+                        MOVER | PULLSHER | TRASHMOVER | SPEED => Some(push_preview(&grid, x, y, dir, 0)),
+                        // generators push the cell in front with the generated cell
+                        GENERATOR => grid.get(x - offset.x, y - offset.y).as_ref()
+                            .filter(|back| can_generate(back))
+                            .map(|_| push_preview(&grid, x + offset.x, y + offset.y, dir, 1)),
+                        _ => None,
+                    };
+                    if let Some(preview) = preview {
+                        draw_push_preview(assets, g, &preview, dir, self.mouse_pos);
+                    }
+                }
+            }
 
         // placing
-            if self.place && !is_inside(hotbar_rect.clone(), self.mouse_pos) {
-                let screen_w_half = SCREEN_WIDTH / 2.0;
-                let screen_h_half = SCREEN_HEIGHT / 2.0;
-                let x = (self.mouse_pos.x - screen_w_half) / CELL_SIZE / screen_zoom + screen_x;
-                let y = screen_y - (self.mouse_pos.y - screen_h_half) / CELL_SIZE / screen_zoom;
-                let cell = Cell::new(HOTBAR_ITEMS[self.active_item][self.hotbar_state[self.active_item]].id, self.direction);
-
-                let x = x.floor() as isize;
-                let y = y.floor() as isize;
-
-                let dia = match self.placement_tool {
-                    Tool::Place => 1,
-                    Tool::Rect(d) => d,
-                    Tool::Circle(d) => d,
-                };
-                let half_dia = dia / 2;
-                let x = x - half_dia;
-                let y = y - half_dia;
-
-                let place_cell;
-                let do_place;
-                if let Some(MouseButton::Left) = self.mouse {
-                    place_cell = Some(cell.clone());
-                    do_place = true;
+            if self.place && !self.show_levels && !is_inside(hotbar_rect.clone(), self.mouse_pos) {
+                let cell = self.active_cell();
+                let (x, y) = mouse_cell(self.mouse_pos);
+                let center = self.symmetry_center();
+
+                if let Some(index) = self.active_blueprint {
+                    let blueprint = rotate_grid(&self.blueprints.blueprints[index].grid, self.direction);
+                    let (x, y) = blueprint_origin(&blueprint, (x, y));
+                    blueprint.for_each(|bx, by, cell| {
+                        if let Some(cell) = cell {
+                            draw_ghost_cell(assets, g, x + bx, y + by, cell);
+                        }
+                    });
+                    let max = (x + blueprint.width as isize - 1, y + blueprint.height as isize - 1);
+                    draw_stroke_rect(g, region_rect((x, y), max), Color::from_hex_argb(0x70ffffff), 2.0);
                 }
-                else if let Some(MouseButton::Right) = self.mouse {
-                    place_cell = None;
-                    do_place = true;
+                else if self.placement_tool == Tool::Select {
+                    let start = self.drag_start.unwrap_or((x, y));
+                    draw_stroke_rect(g, region_rect(start, (x, y)), Color::from_hex_argb(0x70ffffff), 2.0);
                 }
-                else {
-                    place_cell = None;
-                    do_place = false;
+                else if is_drag_tool(self.placement_tool) {
+                    let start = self.drag_start.unwrap_or((x, y));
+                    let cell = line_cell(&cell, start, (x, y), self.placement_tool == Tool::Line(true));
+                    for (x, y) in drag_cells(self.placement_tool, start, (x, y)) {
+                        for (x, y, ghost) in self.symmetry.images(center, x, y, Some(&cell)) {
+                            draw_ghost_cell(assets, g, x, y, ghost.as_ref().unwrap());
+                        }
+                    }
+                }
+                else if self.placement_tool == Tool::Fill {
+                    for (x, y, ghost) in self.symmetry.images(center, x, y, Some(&cell)) {
+                        draw_ghost_cell(assets, g, x, y, ghost.as_ref().unwrap());
+                    }
                 }
+                else {
+                    let dia = match self.placement_tool {
+                        Tool::Place => 1,
+                        Tool::Rect(d) => d,
+                        Tool::Circle(d) => d,
+                        _ => unreachable!(),
+                    };
+                    let half_dia = dia / 2;
+                    let x = x - half_dia;
+                    let y = y - half_dia;
+
+                    // puzzles can't be changed while they are running
+                    let locked = grid.puzzle.is_some() && !self.puzzle_edit && !self.is_initial;
+                    let paint_zone = self.puzzle_edit && self.keys.contains(&VirtualKeyCode::LShift);
+
+                    let place_cell;
+                    let do_place;
+                    if locked {
+                        place_cell = None;
+                        do_place = false;
+                    }
+                    else if let Some(MouseButton::Left) = self.mouse {
+                        place_cell = Some(cell.clone());
+                        do_place = true;
+                    }
+                    else if let Some(MouseButton::Right) = self.mouse {
+                        place_cell = None;
+                        do_place = true;
+                    }
+                    else {
+                        place_cell = None;
+                        do_place = false;
+                    }
+                    if do_place {
+                        self.grid_changes += 1;
+                    }
 
-                if let Tool::Circle(_) = self.placement_tool {
-                    let real_half_dia = half_dia as f32 + 0.5;
-                    let sqrad = real_half_dia * real_half_dia;
-                    for oy in 0..dia {
-                        for ox in 0..dia {
-                            let x_dist = ox as f32 + 0.5 - real_half_dia;
-                            let y_dist = oy as f32 + 0.5 - real_half_dia;
-                            if x_dist * x_dist + y_dist * y_dist <= sqrad {
-                                let x = x + ox;
-                                let y = y + oy;
-                                draw_ghost_cell(assets, g, x, y, &cell);
-                                if do_place {
-                                    let place_cell = place_cell.clone();
-                                    let cell = grid.get_mut(x, y);
-                                    // if let Some(cell) = cell {
-                                    //     if cell.id == MAILBOX {
-                                    //         if let Some(ref mut place_cell) = place_cell {
-                                    //             if place_cell.id != MAILBOX {
-                                    //                 let contained = (place_cell.id, place_cell.direction - cell.direction);
-                                    //                 *place_cell = cell.copy();
-                                    //                 place_cell.contained_cell = Some(contained);
-                                    //             }
-                                    //         }
-                                    //     }
-                                    // }
-                                    if place_cell != *cell {
-                                        self.undo_stack.insert(x, y, cell.clone());
-                                        *cell = place_cell;
-                                    }
+                    let mut positions = Vec::new();
+                    if let Tool::Circle(_) = self.placement_tool {
+                        let real_half_dia = half_dia as f32 + 0.5;
+                        let sqrad = real_half_dia * real_half_dia;
+                        for oy in 0..dia {
+                            for ox in 0..dia {
+                                let x_dist = ox as f32 + 0.5 - real_half_dia;
+                                let y_dist = oy as f32 + 0.5 - real_half_dia;
+                                if x_dist * x_dist + y_dist * y_dist <= sqrad {
+                                    positions.push((x + ox, y + oy));
                                 }
                             }
                         }
                     }
-                }
-                else {
-                    for oy in 0..dia {
-                        for ox in 0..dia {
-                            let x = x + ox;
-                            let y = y + oy;
-                            draw_ghost_cell(assets, g, x, y, &cell);
-                            if do_place {
-                                let place_cell = place_cell.clone();
-                                let cell = grid.get_mut(x, y);
-                                // if let Some(cell) = cell {
-                                //     if cell.id == MAILBOX {
-                                //         if let Some(ref mut place_cell) = place_cell {
-                                //             if place_cell.id != MAILBOX {
-                                //                 let contained = (place_cell.id, place_cell.direction - cell.direction);
-                                //                 *place_cell = cell.copy();
-                                //                 place_cell.contained_cell = Some(contained);
-                                //             }
-                                //         }
-                                //     }
-                                // }
-                                if place_cell != *cell {
-                                    self.undo_stack.insert(x, y, cell.clone());
-                                    *cell = place_cell;
-                                }
+                    else {
+                        for oy in 0..dia {
+                            for ox in 0..dia {
+                                positions.push((x + ox, y + oy));
+                            }
+                        }
+                    }
+
+                    for &(x, y) in &positions {
+                        for (x, y, ghost) in self.symmetry.images(center, x, y, Some(&cell)) {
+                            draw_ghost_cell(assets, g, x, y, ghost.as_ref().unwrap());
+                        }
+                    }
+                    if do_place {
+                        for (x, y, place_cell) in stroke_images(&mut self.stroke, self.symmetry, center, &positions, place_cell.as_ref()) {
+                            let cell = grid.get_mut(x, y);
+                            // if let Some(cell) = cell {
+                            //     if cell.id == MAILBOX {
+                            //         if let Some(ref mut place_cell) = place_cell {
+                            //             if place_cell.id != MAILBOX {
+                            //                 let contained = (place_cell.id, place_cell.direction - cell.direction);
+                            //                 *place_cell = cell.copy();
+                            //                 place_cell.contained_cell = Some(contained);
+                            //             }
+                            //         }
+                            //     }
+                            // }
+                            if paint_zone {
+                                set_zone(x, y, place_cell.is_some());
+                            }
+                            else {
+                                edit_cell(&mut self.undo_stack, x, y, cell, place_cell, self.puzzle_edit);
                             }
                         }
                     }
                 }
             }
 
+        // minimap
+            if self.open_item_menu.is_none() && !self.show_blueprints {
+                let version = self.grid_version();
+                if self.minimap.as_ref().is_none_or(|(_, drawn_version, drawn)| *drawn_version != version && drawn.elapsed().as_secs_f32() > MINIMAP_REFRESH) {
+                    let image = render_minimap(&grid, &assets.cell_colors, MINIMAP_SIZE as u32);
+                    let handle = g.create_image_from_raw_pixels(ImageDataType::RGBA, ImageSmoothingMode::NearestNeighbor, Vector2::new(image.width(), image.height()), &image.into_raw()).unwrap();
+                    self.minimap = Some((handle, version, Instant::now()));
+                }
+                draw_minimap(g, &self.minimap.as_ref().unwrap().0);
+            }
+
         // hotbar
             // background
             g.draw_rectangle(
@@ -361,8 +939,8 @@ impl WindowHandler for WinHandler {
 
             // cells
             #[allow(clippy::needless_range_loop)]
-            for i in 0..HOTBAR_ITEMS.len() {
-                let item = HOTBAR_ITEMS[i];
+            for i in 0..self.hotbar.len() {
+                let item = self.hotbar[i];
                 let active_cell = item[self.hotbar_state[i]];
                 let cell_img = &assets.cells.get(&active_cell.id).unwrap()[usize::from(self.direction)];
                 let rect = Rectangle::new(
@@ -376,10 +954,11 @@ impl WindowHandler for WinHandler {
                     ),
                 );
                 g.draw_rectangle_image_tinted(
-                    rect,
+                    rect.clone(),
                     Color::from_hex_argb(if self.active_item == i { 0xffffffff } else { 0x70ffffff }),
                     cell_img,
                 );
+                draw_inventory_count(assets, g, &rect, active_cell.id);
             }
 
             // active tool
@@ -387,6 +966,14 @@ impl WindowHandler for WinHandler {
                 Tool::Place => &assets.tool_place,
                 Tool::Rect(_) => &assets.tool_rect,
                 Tool::Circle(_) => &assets.tool_circle,
+                Tool::Fill => &assets.tool_fill,
+                Tool::Line(false) => &assets.tool_line,
+                Tool::Line(true) => &assets.tool_line_oriented,
+                Tool::DragRect(false) => &assets.tool_drag_rect,
+                Tool::DragRect(true) => &assets.tool_drag_rect_filled,
+                Tool::DragEllipse(false) => &assets.tool_drag_ellipse,
+                Tool::DragEllipse(true) => &assets.tool_drag_ellipse_filled,
+                Tool::Select => &assets.tool_select,
             };
             let tool_rect = Rectangle::new(
                 Vector2::new(
@@ -413,10 +1000,10 @@ impl WindowHandler for WinHandler {
 
             // open item menu
             if let Some(i1) = self.open_item_menu {
-                if i1 < HOTBAR_ITEMS.len() {
+                if i1 < self.hotbar.len() {
                     let img_x = i1 as f32 * (HOTBAR_CELL_SIZE + HOTBAR_CELL_SPACING) + HOTBAR_CELL_SPACING;
-                    for i2 in 0..HOTBAR_ITEMS[i1].len() {
-                        let id = HOTBAR_ITEMS[i1][i2].id;
+                    for i2 in 0..self.hotbar[i1].len() {
+                        let id = self.hotbar[i1][i2].id;
                         let cell_img = &assets.cells.get(&id).unwrap()[usize::from(self.direction)];
                         let rect = Rectangle::new(
                             Vector2::new(
@@ -433,6 +1020,7 @@ impl WindowHandler for WinHandler {
                             Color::from_hex_argb(if self.hotbar_state[i1] == i2 { 0xffffffff } else { 0x7fffffff }),
                             cell_img,
                         );
+                        draw_inventory_count(assets, g, &rect, id);
                         if is_inside(rect.clone(), self.mouse_pos) {
                             let position = rect.top_right() + Vector2::new(HOTBAR_CELL_SPACING, 0.0);
                             self.hotbar_item_text.as_ref().unwrap().get(&id).unwrap().draw(g, position);
@@ -440,24 +1028,22 @@ impl WindowHandler for WinHandler {
                     }
                 }
                 else {
-                    let img_x = SCREEN_WIDTH - HOTBAR_CELL_SIZE - HOTBAR_CELL_SPACING;
-                    for i2 in 0..3 {
+                    for i2 in 0..TOOL_COUNT {
                         let img = match i2 {
                             0 => &assets.tool_place,
                             1 => &assets.tool_rect,
                             2 => &assets.tool_circle,
+                            3 => &assets.tool_fill,
+                            4 => &assets.tool_line,
+                            5 => &assets.tool_line_oriented,
+                            6 => &assets.tool_drag_rect,
+                            7 => &assets.tool_drag_rect_filled,
+                            8 => &assets.tool_drag_ellipse,
+                            9 => &assets.tool_drag_ellipse_filled,
+                            10 => &assets.tool_select,
                             _ => unreachable!(),
                         };
-                        let rect = Rectangle::new(
-                            Vector2::new(
-                                img_x,
-                                SCREEN_HEIGHT - HOTBAR_HEIGHT - HOTBAR_CELL_SIZE - HOTBAR_CELL_SPACING - (i2 as f32 * (HOTBAR_CELL_SPACING + HOTBAR_CELL_SIZE)),
-                            ),
-                            Vector2::new(
-                                img_x + HOTBAR_CELL_SIZE,
-                                SCREEN_HEIGHT - HOTBAR_HEIGHT - HOTBAR_CELL_SPACING - (i2 as f32 * (HOTBAR_CELL_SPACING + HOTBAR_CELL_SIZE)),
-                            ),
-                        );
+                        let rect = tool_menu_rect(i2);
                         g.draw_rectangle_image_tinted(
                             rect.clone(),
                             Color::from_hex_argb(if tool_to_index(self.placement_tool) == i2 { 0xffffffff } else { 0x7fffffff }),
@@ -473,6 +1059,11 @@ impl WindowHandler for WinHandler {
 
         }
 
+        // statistics
+        if self.show_stats {
+            unsafe { draw_stats(assets, g, &self.stats); }
+        }
+
         // help
         if self.show_help {
             unsafe {
@@ -484,10 +1075,11 @@ impl WindowHandler for WinHandler {
                     help_rect,
                     Color::from_hex_argb(0xee444444),
                 );
+                let help_text = self.help_text.as_ref().unwrap();
                 g.draw_text(
-                    Vector2::new(0.0, SCREEN_HEIGHT / 2.0),
+                    Vector2::new(0.0, ((SCREEN_HEIGHT - help_text.height()) / 2.0).max(0.0)),
                     Color::WHITE,
-                    self.help_text.as_ref().unwrap(),
+                    help_text,
                 );
             }
         }
@@ -507,6 +1099,11 @@ impl WindowHandler for WinHandler {
             &assets.font.layout_text(&format!("Tick time: {tick_time}"), 17.0, TextOptions::new()),
         );
 
+        // recording
+        if let Some(recording) = &self.recording {
+            draw_record_panel(assets, g, recording, self.find.is_some(), self.mouse_pos);
+        }
+
         // tps
         g.draw_text(
             Vector2::new(10.0, 50.0),
@@ -532,11 +1129,184 @@ impl WindowHandler for WinHandler {
             );
         }
 
+        // update order
+        unsafe {
+            if grid.update_order != UpdateOrder::Quell {
+                g.draw_text(
+                    Vector2::new(10.0, 110.0),
+                    Color::WHITE,
+                    &assets.font.layout_text(&format!("Update order: {}", grid.update_order.name()), 17.0, TextOptions::new()),
+                );
+            }
+        }
+
+        // symmetry
+        if self.symmetry != Symmetry::None {
+            g.draw_text(
+                Vector2::new(10.0, 130.0),
+                Color::WHITE,
+                &assets.font.layout_text(&format!("Symmetry: {}", self.symmetry.name()), 17.0, TextOptions::new()),
+            );
+        }
+
+        // heatmap
+        if self.show_heatmap {
+            unsafe {
+                let ticks = grid.activity.as_ref().map_or(0, Activity::ticks);
+                g.draw_text(
+                    Vector2::new(10.0, 150.0),
+                    Color::WHITE,
+                    &assets.font.layout_text(&format!("Activity over the last {ticks} ticks"), 17.0, TextOptions::new()),
+                );
+            }
+        }
+
+        // puzzle
+        unsafe {
+            if let Some(puzzle) = &grid.puzzle {
+                let text = if self.solved && self.current_level.is_some_and(|index| index + 1 < self.levels.levels.len()) {
+                    "Puzzle solved! Press Enter for the next level".to_string()
+                }
+                else if self.solved {
+                    "Puzzle solved!".to_string()
+                }
+                else if self.puzzle_edit {
+                    let mut text = format!("Puzzle editor - Goal: {}\nShift+click to edit the build zone, +/- to change the inventory", puzzle.goal.description());
+                    if let Some(problem) = puzzle.goal.problem(if self.is_initial { &grid } else { &initial }) {
+                        text.push_str(&format!("\nThe puzzle can't be solved: {problem}"));
+                    }
+                    text
+                }
+                else {
+                    format!("Goal: {}", puzzle.goal.description())
+                };
+                let text = assets.font.layout_text(&text, 20.0, TextOptions::new().with_wrap_to_width(SCREEN_WIDTH, TextAlignment::Center));
+                g.draw_text(
+                    Vector2::new(0.0, 10.0),
+                    if self.solved { Color::GREEN } else { Color::WHITE },
+                    &text,
+                );
+            }
+        }
+
+        // subtick times
+        if self.show_profile {
+            unsafe {
+                let profile = grid.profile.as_deref().unwrap_or_default();
+                let max_time = profile.iter().map(|s| s.time).fold(0.0, f32::max).max(0.001);
+                let mut y = 170.0;
+                for subtick in profile {
+                    g.draw_rectangle(
+                        Rectangle::new(
+                            Vector2::new(10.0, y + 2.0),
+                            Vector2::new(10.0 + subtick.time / max_time * PROFILE_BAR_WIDTH, y + 16.0),
+                        ),
+                        Color::from_hex_argb(0x9900aaff),
+                    );
+                    g.draw_text(
+                        Vector2::new(10.0, y),
+                        Color::WHITE,
+                        &assets.font.layout_text(&format!("{}: {:.3} ms ({} cells)", subtick.name, subtick.time, subtick.cells), 17.0, TextOptions::new()),
+                    );
+                    y += 20.0;
+                }
+            }
+        }
+
+        // blueprints
+        if self.show_blueprints {
+            for blueprint in &self.blueprints.blueprints {
+                if !self.blueprint_thumbnails.contains_key(&blueprint.name) {
+                    let image = blueprint.thumbnail.clone().unwrap_or_else(|| render_thumbnail(&blueprint.grid, &assets.cell_colors));
+                    let handle = g.create_image_from_raw_pixels(ImageDataType::RGBA, ImageSmoothingMode::NearestNeighbor, Vector2::new(image.width(), image.height()), &image.into_raw()).unwrap();
+                    self.blueprint_thumbnails.insert(blueprint.name.clone(), handle);
+                }
+            }
+            unsafe { draw_blueprint_panel(assets, g, &self.settings, &self.blueprints, &self.blueprint_thumbnails, &self.blueprint_search, self.mouse_pos); }
+        }
+        if let Some(name) = &self.blueprint_name {
+            unsafe {
+                let text = assets.font.layout_text(
+                    &format!("Blueprint name: {name}_\nEnter to save, ESC to cancel"),
+                    25.0,
+                    TextOptions::new().with_wrap_to_width(SCREEN_WIDTH, TextAlignment::Center),
+                );
+                let top = (SCREEN_HEIGHT - text.height()) / 2.0;
+                g.draw_rectangle(
+                    Rectangle::new(Vector2::new(0.0, top - 20.0), Vector2::new(SCREEN_WIDTH, top + text.height() + 20.0)),
+                    Color::from_hex_argb(0xee444444),
+                );
+                g.draw_text(Vector2::new(0.0, top), Color::WHITE, &text);
+            }
+        }
+
+        // subtick mode
+        if self.subtick_mode {
+            unsafe {
+                let text = match self.last_subtick {
+                    Some((name, (done, total))) if done == total => format!("Subtick mode - {name} ({done}/{total}), tick finished"),
+                    Some((name, (done, total))) => format!("Subtick mode - {name} ({done}/{total})"),
+                    None => match key_hints(&self.settings, &[(Action::Step, "to run the next subtick"), (Action::SubtickMode, "to exit")]) {
+                        hints if hints.is_empty() => "Subtick mode".to_string(),
+                        hints => format!("Subtick mode - {hints}"),
+                    },
+                };
+                let text = assets.font.layout_text(&text, 20.0, TextOptions::new().with_wrap_to_width(SCREEN_WIDTH, TextAlignment::Center));
+                g.draw_text(
+                    Vector2::new(0.0, SCREEN_HEIGHT - HOTBAR_HEIGHT - text.height() - 30.0),
+                    Color::from_hex_rgb(0xffa060),
+                    &text,
+                );
+            }
+        }
+
+        // coordinates and inspector
+        if !self.show_levels {
+            unsafe {
+                let hotbar_top = SCREEN_HEIGHT - HOTBAR_HEIGHT;
+                if self.mouse_pos.y < hotbar_top {
+                    let (x, y) = mouse_cell(self.mouse_pos);
+                    let text = assets.font.layout_text(&format!("{x}, {y}"), 17.0, TextOptions::new());
+                    g.draw_text(Vector2::new(SCREEN_WIDTH - text.width() - 10.0, hotbar_top - text.height() - 5.0), Color::WHITE, &text);
+
+                    if self.show_inspector {
+                        if let Some(cell) = grid.get(x, y) {
+                            draw_inspector(assets, g, x, y, cell, self.mouse_pos);
+                        }
+                    }
+                }
+            }
+        }
+
+        // find and replace
+        if let Some(find) = &self.find {
+            unsafe {
+                let version = self.grid_version();
+                let count = match self.find_count {
+                    Some((query, counted, count)) if query == find.0 && counted == version => count,
+                    _ => {
+                        let count = find.0.find(&grid).len();
+                        self.find_count = Some((find.0, version, count));
+                        count
+                    },
+                };
+                draw_find_dialog(assets, g, find, count, self.mouse_pos);
+            }
+        }
+
+        // level browser
+        if self.show_levels {
+            unsafe { draw_level_browser(assets, g, &self.settings, &self.levels, self.current_level, self.level_scroll); }
+        }
+
         helper.request_redraw();
 	}
 
     fn on_key_down(&mut self, window: &mut WindowHelper<()>, virtual_key_code: Option<VirtualKeyCode>, _: KeyScancode) {
         fn set_running(this: &mut WinHandler, running: bool) {
+            if running {
+                unsafe { this.finish_subticks(); }
+            }
             if running && this.is_initial {
                 this.is_initial = false;
                 unsafe { initial = grid.clone(); }
@@ -560,68 +1330,198 @@ impl WindowHandler for WinHandler {
         }
 
         if let Some(key) = virtual_key_code {
+            if self.blueprint_name.is_some() || self.show_blueprints {
+                self.text_key_down(key);
+                return;
+            }
+
             self.keys.insert(key);
-            match key {
-                VirtualKeyCode::Q if self.keys.contains(&COMMAND_KEY) => {
-                    window.terminate_loop();
+            let Some(action) = self.settings.keymap.action(key, &self.keys) else { return };
+            // the grid behind the level selection can't be changed
+            if self.show_levels && !matches!(action, Action::Quit | Action::Close | Action::Levels) { return; }
+            match action {
+                Action::Quit => window.terminate_loop(),
+
+                Action::Undo => {
+                    // a running puzzle can't be changed outside of the build zone
+                    let locked = unsafe { grid.puzzle.is_some() } && !self.puzzle_edit && !self.is_initial;
+                    if !locked {
+                        if let Some(action) = self.undo_stack.pop() {
+                            action.undo_on(unsafe { &mut grid });
+                            self.grid_changes += 1;
+                        }
+                    }
                 },
 
-                VirtualKeyCode::Z if self.keys.contains(&COMMAND_KEY) => {
-                    if let Some(action) = self.undo_stack.pop() {
-                        action.undo_on(unsafe { &mut grid });
+                Action::SmoothMovement => self.smooth = !self.smooth,
+
+                Action::CenterSelection => {
+                    if let Some((min, max)) = self.selection {
+                        unsafe {
+                            screen_x = (min.0 + max.0 + 1) as f32 / 2.0;
+                            screen_y = (min.1 + max.1 + 1) as f32 / 2.0;
+                        }
+                    }
+                },
+                Action::FitGrid => unsafe { fit_grid() },
+                Action::SaveBookmark(index) => unsafe {
+                    let bookmark = Some(Bookmark { x: screen_x, y: screen_y, zoom: screen_zoom });
+                    grid.bookmarks[index] = bookmark;
+                    // keep the bookmark when the grid is reset
+                    initial.bookmarks[index] = bookmark;
+                },
+                Action::JumpToBookmark(index) => unsafe {
+                    if let Some(bookmark) = grid.bookmarks[index] {
+                        screen_x = bookmark.x;
+                        screen_y = bookmark.y;
+                        screen_zoom = bookmark.zoom;
                     }
                 },
 
-                VirtualKeyCode::Escape => self.show_help = !self.show_help,
-
-                VirtualKeyCode::Space => { set_running(self, !self.running) },
-                VirtualKeyCode::G => { if !self.running { unsafe { do_tick(); } } },
-                VirtualKeyCode::T => {
+                Action::Close if self.show_levels => self.show_levels = false,
+                Action::Close if self.find.is_some() => self.find = None,
+                Action::Close if self.active_blueprint.is_some() => self.active_blueprint = None,
+                Action::Close => self.show_help = !self.show_help,
+                Action::Levels => self.show_levels = !self.show_levels,
+                Action::Blueprints => {
+                    self.show_blueprints = true;
+                    self.blueprint_search.clear();
+                    self.ignore_char = true;
+                },
+                Action::SymmetryCenter => unsafe {
+                    // the center of the cell under the mouse, or back to the grid center
+                    let (x, y) = mouse_cell(self.mouse_pos);
+                    let center = (x * 2 + 1, y * 2 + 1);
+                    self.symmetry_center = if self.symmetry_center == Some(center) { None } else { Some(center) };
+                },
+                Action::Symmetry => self.symmetry = self.symmetry.next(),
+                Action::SaveBlueprint if self.selection.is_some() => {
+                    self.blueprint_name = Some(String::new());
+                    self.ignore_char = true;
+                },
+                Action::Confirm if self.find.is_some() => self.replace_all(),
+                Action::Confirm => {
+                    if let Some(index) = self.current_level {
+                        if index + 1 < self.levels.levels.len() {
+                            self.load_level(index + 1);
+                        }
+                    }
+                },
+                Action::Heatmap => self.show_heatmap = !self.show_heatmap,
+                Action::Stats => self.show_stats = !self.show_stats,
+                Action::Profile => self.show_profile = !self.show_profile,
+                Action::Inspector => self.show_inspector = !self.show_inspector,
+
+                Action::Start => { set_running(self, !self.running) },
+                Action::Step if self.subtick_mode && !self.running => unsafe { self.step_subtick() },
+                Action::Step => { if !self.running { unsafe { do_tick(); } } },
+                Action::SubtickMode => {
+                    self.subtick_mode = !self.subtick_mode;
+                    if !self.subtick_mode {
+                        unsafe { self.finish_subticks(); }
+                    }
+                },
+                Action::Reset => {
                     if !self.is_initial {
                         set_running(self, false);
                         unsafe { grid = initial.clone(); }
+                        self.grid_changes += 1;
+                        self.stepper = None;
+                        self.last_subtick = None;
+                        self.subtick_changes.clear();
                         self.is_initial = true;
                         self.loop_length = 0;
+                        self.solved = false;
                     }
                 },
 
-                VirtualKeyCode::Q => self.direction -= 1,
-                VirtualKeyCode::E => self.direction += 1,
+                Action::RotateLeft => self.direction -= 1,
+                Action::RotateRight => self.direction += 1,
 
-                VirtualKeyCode::R => unsafe { if self.keys.contains(&VirtualKeyCode::LAlt) { scale_tool(&mut self.placement_tool, -2) } else { screen_zoom /= 1.2 } },
-                VirtualKeyCode::F => unsafe { if self.keys.contains(&VirtualKeyCode::LAlt) { scale_tool(&mut self.placement_tool,  2) } else { screen_zoom *= 1.2 } },
+                Action::Find => {
+                    self.find = match self.find {
+                        Some(_) => None,
+                        None => Some((CellQuery::new(self.active_cell().id()), Replacement::Change(None, None))),
+                    };
+                },
+                Action::Record => self.toggle_recording(),
+                Action::ShrinkTool => scale_tool(&mut self.placement_tool, -2),
+                Action::GrowTool => scale_tool(&mut self.placement_tool, 2),
+                Action::ZoomOut => unsafe { screen_zoom /= self.settings.zoom_speed },
+                Action::ZoomIn => unsafe { screen_zoom *= self.settings.zoom_speed },
 
-                VirtualKeyCode::I => {
+                Action::Import => {
                     let mut clip: ClipboardContext = ClipboardProvider::new().unwrap();
                     let text = clip.get_contents().unwrap();
-                    unsafe {
-                        grid = import(text.as_str()).unwrap();
-                        screen_x = grid.width as f32 / 2.0;
-                        screen_y = grid.height as f32 / 2.0;
-                        screen_zoom = 1.0;
-                    }
+                    self.load_grid(import(text.as_str()).unwrap());
                 },
-                VirtualKeyCode::O => {
+                Action::ExportImage => self.export_image(),
+                Action::ExportQ1 => {
                     let mut clip: ClipboardContext = ClipboardProvider::new().unwrap();
                     let text = unsafe { export_q1(&grid) };
                     clip.set_contents(text).unwrap();
                 },
-                VirtualKeyCode::P => {
+                Action::ExportQ2 => {
                     let mut clip: ClipboardContext = ClipboardProvider::new().unwrap();
                     let text = unsafe { export_q2(&grid) };
                     clip.set_contents(text).unwrap();
                 },
 
-                VirtualKeyCode::M => {
+                Action::Threaded => {
                     if !self.running {
                         self.threaded = !self.threaded;
+                        self.settings.threaded = self.threaded;
+                        self.settings.save();
                     }
                 },
 
-                VirtualKeyCode::N => {
+                Action::UpdateOrder if self.is_initial => unsafe { grid.update_order = grid.update_order.next() },
+
+                Action::RemovePuzzle if self.is_initial => unsafe {
+                    grid.puzzle = None;
+                    self.puzzle_edit = false;
+                },
+                Action::PuzzleEditor if self.is_initial => unsafe {
+                    if grid.puzzle.is_none() {
+                        grid.puzzle = Some(Puzzle::new(grid.width, grid.height));
+                        self.puzzle_edit = true;
+                    }
+                    else {
+                        self.puzzle_edit = !self.puzzle_edit;
+                    }
+                },
+                Action::AddItem | Action::RemoveItem if self.puzzle_edit => unsafe {
+                    if let Some(puzzle) = &mut grid.puzzle {
+                        let id = self.hotbar[self.active_item][self.hotbar_state[self.active_item]].id;
+                        if action == Action::AddItem {
+                            puzzle.put_back(id);
+                        }
+                        else if !puzzle.take(id) || puzzle.available(id) == 0 {
+                            puzzle.inventory.remove(&id);
+                        }
+                    }
+                },
+
+                Action::PlayerRight | Action::PlayerDown | Action::PlayerLeft | Action::PlayerUp if !self.is_initial => {
+                    let dir = match action {
+                        Action::PlayerRight => Direction::Right,
+                        Action::PlayerDown => Direction::Down,
+                        Action::PlayerLeft => Direction::Left,
+                        _ => Direction::Up,
+                    };
+                    // the update thread picks the input up from the shared grid
+                    match &self.running_state {
+                        Some(state) => state.lock().unwrap().1.queue_input(dir),
+                        None => unsafe { grid.queue_input(dir) },
+                    }
+                },
+
+                Action::CheckLoop => {
                     if self.is_initial {
                         self.check_loop = !self.check_loop;
                         self.loop_length = 0;
+                        self.settings.check_loop = self.check_loop;
+                        self.settings.save();
                     }
                 }
 
@@ -636,16 +1536,80 @@ impl WindowHandler for WinHandler {
         }
     }
 
-    fn on_mouse_button_down(&mut self, _: &mut WindowHelper<()>, button: MouseButton) {
-        self.mouse = Some(button);
+    fn on_keyboard_char(&mut self, _: &mut WindowHelper<()>, unicode_codepoint: char) {
+        if std::mem::take(&mut self.ignore_char) || unicode_codepoint.is_control() { return; }
+        if let Some(name) = &mut self.blueprint_name {
+            name.push(unicode_codepoint);
+        }
+        else if self.show_blueprints {
+            self.blueprint_search.push(unicode_codepoint);
+        }
+    }
+
+    fn on_mouse_button_down(&mut self, _: &mut WindowHelper<()>, button: MouseButton) {
+        self.mouse = Some(button);
+
+        if self.show_levels {
+            self.place = false;
+            if button == MouseButton::Left {
+                let clicked = (0..self.levels.levels.len()).find(|&i| is_inside(unsafe { level_rect(i, self.level_scroll) }, self.mouse_pos));
+                if let Some(index) = clicked {
+                    self.load_level(index);
+                }
+            }
+            return;
+        }
+
+        if button == MouseButton::Middle {
+            self.pan_from = Some(self.mouse_pos);
+            self.place = false;
+            return;
+        }
+
+        if self.show_blueprints {
+            let results = self.blueprints.search(&self.blueprint_search);
+            let rows = unsafe { blueprint_rows() };
+            if (0..rows.min(results.len() + 1)).any(|row| is_inside(unsafe { blueprint_rect(row) }, self.mouse_pos)) {
+                self.place = false;
+                let clicked = results.iter().take(rows.saturating_sub(1)).enumerate().find(|&(row, _)| is_inside(unsafe { blueprint_rect(row + 1) }, self.mouse_pos));
+                if let (Some((_, &index)), MouseButton::Left) = (clicked, button) {
+                    self.active_blueprint = Some(index);
+                    self.show_blueprints = false;
+                }
+                return;
+            }
+            self.show_blueprints = false;
+        }
+
+        if self.open_item_menu.is_none() && !self.show_blueprints && is_inside(unsafe { minimap_rect() }, self.mouse_pos) {
+            self.place = false;
+            self.minimap_drag = button == MouseButton::Left;
+            return;
+        }
+
+        if self.find.is_some() {
+            if let Some(row) = (0..FIND_ROWS).find(|&row| is_inside(find_row_rect(row), self.mouse_pos)) {
+                self.find_click(row, button);
+                self.place = false;
+                return;
+            }
+        }
+
+        if self.recording.is_some() {
+            if let Some(row) = (0..RECORD_ROWS).find(|&row| is_inside(record_row_rect(row, self.find.is_some()), self.mouse_pos)) {
+                self.record_click(row, button);
+                self.place = false;
+                return;
+            }
+        }
 
         unsafe {
-            let len = HOTBAR_ITEMS.len();
+            let len = self.hotbar.len();
 
             if let Some(i1) = self.open_item_menu {
                 if i1 < len {
                     let img_x = i1 as f32 * (HOTBAR_CELL_SIZE + HOTBAR_CELL_SPACING) + HOTBAR_CELL_SPACING;
-                    for i2 in 0..HOTBAR_ITEMS[i1].len() {
+                    for i2 in 0..self.hotbar[i1].len() {
                         let rect = Rectangle::new(
                             Vector2::new(
                                 img_x,
@@ -663,23 +1627,21 @@ impl WindowHandler for WinHandler {
                     }
                 }
                 else {
-                    let img_x = SCREEN_WIDTH - HOTBAR_CELL_SPACING - HOTBAR_CELL_SIZE;
-                    for i2 in 0..3 {
-                        let rect = Rectangle::new(
-                            Vector2::new(
-                                img_x,
-                                SCREEN_HEIGHT - HOTBAR_HEIGHT - HOTBAR_CELL_SIZE - HOTBAR_CELL_SPACING - (i2 as f32 * (HOTBAR_CELL_SPACING + HOTBAR_CELL_SIZE)),
-                            ),
-                            Vector2::new(
-                                img_x + HOTBAR_CELL_SIZE,
-                                SCREEN_HEIGHT - HOTBAR_HEIGHT - HOTBAR_CELL_SPACING - (i2 as f32 * (HOTBAR_CELL_SPACING + HOTBAR_CELL_SIZE)),
-                            ),
-                        );
+                    for i2 in 0..TOOL_COUNT {
+                        let rect = tool_menu_rect(i2);
                         if is_inside(rect, self.mouse_pos) && button == MouseButton::Left {
                             self.placement_tool = match i2 {
                                 0 => Tool::Place,
                                 1 => Tool::Rect(5),
                                 2 => Tool::Circle(5),
+                                3 => Tool::Fill,
+                                4 => Tool::Line(false),
+                                5 => Tool::Line(true),
+                                6 => Tool::DragRect(false),
+                                7 => Tool::DragRect(true),
+                                8 => Tool::DragEllipse(false),
+                                9 => Tool::DragEllipse(true),
+                                10 => Tool::Select,
                                 _ => unreachable!(),
                             };
                             self.place = false;
@@ -729,20 +1691,109 @@ impl WindowHandler for WinHandler {
             }
 
             if self.place {
-                self.undo_stack.start();
+                self.undo_stack.start(&grid);
+                self.stroke.clear();
+                self.grid_changes += 1;
+
+                let locked = grid.puzzle.is_some() && !self.puzzle_edit && !self.is_initial;
+                let pos = mouse_cell(self.mouse_pos);
+                match self.placement_tool {
+                    _ if self.active_blueprint.is_some() => {
+                        if button == MouseButton::Right {
+                            self.active_blueprint = None;
+                        }
+                        else if !locked {
+                            let blueprint = rotate_grid(&self.blueprints.blueprints[self.active_blueprint.unwrap()].grid, self.direction);
+                            let (x, y) = blueprint_origin(&blueprint, pos);
+                            // empty positions of the blueprint keep the cells below
+                            blueprint.for_each(|bx, by, cell| {
+                                if let Some(cell) = cell {
+                                    edit_cell(&mut self.undo_stack, x + bx, y + by, grid.get_mut(x + bx, y + by), Some(cell.clone()), self.puzzle_edit);
+                                }
+                            });
+                        }
+                    },
+                    Tool::Select if button == MouseButton::Right => self.selection = None,
+                    Tool::Select => self.drag_start = Some(pos),
+                    Tool::Fill if !locked => {
+                        let place_cell = match button {
+                            MouseButton::Left => Some(self.active_cell()),
+                            _ => None,
+                        };
+                        // every mirrored start position is filled separately
+                        for (index, (x, y, place_cell)) in self.symmetry.images(self.symmetry_center(), pos.0, pos.1, place_cell.as_ref()).into_iter().enumerate() {
+                            for (x, y) in fill_area(&grid, x, y) {
+                                if claim_position(&mut self.stroke, (x, y), index) {
+                                    edit_cell(&mut self.undo_stack, x, y, grid.get_mut(x, y), place_cell.clone(), self.puzzle_edit);
+                                }
+                            }
+                        }
+                    },
+                    tool if is_drag_tool(tool) && !locked => self.drag_start = Some(pos),
+                    _ => {},
+                }
             }
         }
 
     }
-    fn on_mouse_button_up(&mut self, _: &mut WindowHelper<()>, _: MouseButton) {
+    fn on_mouse_button_up(&mut self, _: &mut WindowHelper<()>, button: MouseButton) {
+        if button == MouseButton::Middle {
+            self.pan_from = None;
+            self.place = true;
+            self.mouse = None;
+            return;
+        }
+        if let (Some(start), Tool::Select) = (self.drag_start, self.placement_tool) {
+            let end = unsafe { mouse_cell(self.mouse_pos) };
+            self.selection = Some(((start.0.min(end.0), start.1.min(end.1)), (start.0.max(end.0), start.1.max(end.1))));
+            self.drag_start = None;
+        }
+        if let Some(start) = self.drag_start.take() {
+            let end = unsafe { mouse_cell(self.mouse_pos) };
+            let place_cell = match button {
+                MouseButton::Left => Some(line_cell(&self.active_cell(), start, end, self.placement_tool == Tool::Line(true))),
+                _ => None,
+            };
+            let paint_zone = self.puzzle_edit && self.keys.contains(&VirtualKeyCode::LShift);
+            let center = unsafe { self.symmetry_center() };
+            self.grid_changes += 1;
+            let positions = drag_cells(self.placement_tool, start, end);
+            for (x, y, place_cell) in stroke_images(&mut self.stroke, self.symmetry, center, &positions, place_cell.as_ref()) {
+                unsafe {
+                    if paint_zone {
+                        set_zone(x, y, place_cell.is_some());
+                    }
+                    else {
+                        edit_cell(&mut self.undo_stack, x, y, grid.get_mut(x, y), place_cell, self.puzzle_edit);
+                    }
+                }
+            }
+        }
         self.place = true;
+        self.minimap_drag = false;
         self.mouse = None;
     }
     fn on_mouse_move(&mut self, _: &mut WindowHelper<()>, position: Vector2<f32>) {
+        if let Some(from) = self.pan_from.replace(position) {
+            unsafe {
+                screen_x -= (position.x - from.x) / CELL_SIZE / screen_zoom;
+                screen_y += (position.y - from.y) / CELL_SIZE / screen_zoom;
+            }
+        }
         self.mouse_pos = position;
     }
 
     fn on_mouse_wheel_scroll(&mut self, _: &mut WindowHelper<()>, distance: MouseScrollDistance) {
+        if self.show_levels {
+            let y = match distance {
+                MouseScrollDistance::Lines { y, .. } => y as f32 * 30.0,
+                MouseScrollDistance::Pixels { y, .. } => y as f32,
+                MouseScrollDistance::Pages { y, .. } => y as f32 * unsafe { SCREEN_HEIGHT },
+            };
+            self.level_scroll = (self.level_scroll - y).max(0.0);
+            return;
+        }
+
         unsafe {
             match distance {
                 MouseScrollDistance::Lines { x: _, y, z: _ } => {
@@ -754,11 +1805,8 @@ impl WindowHandler for WinHandler {
                             scale_tool(&mut self.placement_tool, 2);
                         }
                     }
-                    else if y > 0.0 {
-                        screen_zoom *= 1.2f64.powf(y.abs()) as f32;
-                    }
                     else {
-                        screen_zoom /= 1.2f64.powf(y.abs()) as f32;
+                        zoom_at(self.settings.zoom_speed.powf(y as f32), self.mouse_pos);
                     }
                 },
                 MouseScrollDistance::Pixels { x: _, y, z: _ } => {
@@ -770,11 +1818,8 @@ impl WindowHandler for WinHandler {
                             scale_tool(&mut self.placement_tool, 2);
                         }
                     }
-                    else if y > 0.0 {
-                        screen_zoom *= 1.0 + (y.abs() as f32 / 100.0);
-                    }
                     else {
-                        screen_zoom /= 1.0 + (y.abs() as f32 / 100.0);
+                        zoom_at(self.settings.zoom_speed.powf(y as f32 / PIXELS_PER_ZOOM_STEP), self.mouse_pos);
                     }
                 },
                 _ => {}
@@ -783,6 +1828,75 @@ impl WindowHandler for WinHandler {
     }
 }
 
+/// Draws the amount of cells left in the puzzle inventory onto a hotbar slot.
+unsafe fn draw_inventory_count(assets: &Assets, g: &mut Graphics2D, rect: &Rectangle, id: CellType) {
+    if let Some(puzzle) = &grid.puzzle {
+        let text = assets.font.layout_text(&puzzle.available(id).to_string(), HOTBAR_CELL_SIZE / 2.5, TextOptions::new());
+        g.draw_text(
+            rect.bottom_right() - Vector2::new(text.width() + 2.0, text.height()),
+            Color::WHITE,
+            &text,
+        );
+    }
+}
+
+/// Records that a symmetry image places a cell at the position during the current stroke.
+/// Returns false if an earlier image already placed a cell there, which is kept.
+fn claim_position(stroke: &mut HashMap<(isize, isize), usize>, pos: (isize, isize), index: usize) -> bool {
+    let earlier = stroke.entry(pos).or_insert(index);
+    if *earlier < index { return false; }
+    *earlier = index;
+    true
+}
+
+/// The symmetry images of placing a cell at the positions, first every placement itself, then the mirrored ones.
+/// Positions an earlier image placed during the stroke are left out, so overlapping images don't overwrite each other.
+fn stroke_images(stroke: &mut HashMap<(isize, isize), usize>, symmetry: Symmetry, center: (isize, isize), positions: &[(isize, isize)], cell: Option<&Cell>) -> Vec<(isize, isize, Option<Cell>)> {
+    let images: Vec<_> = positions.iter().map(|&(x, y)| symmetry.images(center, x, y, cell)).collect();
+    let count = images.iter().map(Vec::len).max().unwrap_or(0);
+    let mut placements = Vec::new();
+    for index in 0..count {
+        for (x, y, cell) in images.iter().filter_map(|images| images.get(index)) {
+            if claim_position(stroke, (*x, *y), index) {
+                placements.push((*x, *y, cell.clone()));
+            }
+        }
+    }
+    placements
+}
+
+/// Places a cell on the grid. Outside of the puzzle editor
+/// only cells in the build zone can be changed, using the inventory.
+fn edit_cell(undo_stack: &mut CellUndoStack, x: isize, y: isize, cell: &mut Option<Cell>, place_cell: Option<Cell>, puzzle_edit: bool) {
+    if place_cell == *cell { return; }
+
+    if let Some(puzzle) = unsafe { &mut grid.puzzle } {
+        if !puzzle_edit {
+            if !puzzle.in_zone(x, y) { return; }
+            let old_id = cell.as_ref().map(|cell| cell.id());
+            let new_id = place_cell.as_ref().map(|cell| cell.id());
+            // rotating a cell doesn't need the inventory
+            if old_id != new_id {
+                if let Some(id) = new_id {
+                    if !puzzle.take(id) { return; }
+                }
+                if let Some(id) = old_id {
+                    puzzle.put_back(id);
+                }
+            }
+        }
+    }
+
+    undo_stack.insert(x, y, cell.clone());
+    *cell = place_cell;
+}
+
+unsafe fn set_zone(x: isize, y: isize, value: bool) {
+    if let Some(puzzle) = &mut grid.puzzle {
+        puzzle.set_zone(x, y, value);
+    }
+}
+
 unsafe fn do_tick() {
     update(&mut grid);
 }
@@ -792,6 +1906,8 @@ fn scale_tool(tool: &mut Tool, change: isize) {
         Tool::Place => 1,
         Tool::Rect(v) => v,
         Tool::Circle(v) => v,
+        // the other tools have no size
+        Tool::Fill | Tool::Line(_) | Tool::DragRect(_) | Tool::DragEllipse(_) | Tool::Select => return,
     } + change;
     if value < 1 {
         *tool = Tool::Place;
@@ -802,6 +1918,7 @@ fn scale_tool(tool: &mut Tool, change: isize) {
             (_, Tool::Place) => Tool::Rect(value),
             (value, Tool::Rect(_)) => Tool::Rect(value),
             (value, Tool::Circle(_)) => Tool::Circle(value),
+            (_, tool) => tool,
         }
     }
 }
@@ -811,11 +1928,335 @@ fn tool_to_index(tool: Tool) -> usize {
         Tool::Place => 0,
         Tool::Rect(_) => 1,
         Tool::Circle(_) => 2,
+        Tool::Fill => 3,
+        Tool::Line(false) => 4,
+        Tool::Line(true) => 5,
+        Tool::DragRect(false) => 6,
+        Tool::DragRect(true) => 7,
+        Tool::DragEllipse(false) => 8,
+        Tool::DragEllipse(true) => 9,
+        Tool::Select => 10,
+    }
+}
+
+/// Position of a tool in the tool menu. Columns are stacked upwards from the tool button and grow to the left.
+unsafe fn tool_menu_rect(index: usize) -> Rectangle {
+    let column = (index / TOOLS_PER_COLUMN) as f32;
+    let row = (index % TOOLS_PER_COLUMN) as f32;
+    let right = SCREEN_WIDTH - HOTBAR_CELL_SPACING - column * (HOTBAR_CELL_SPACING + HOTBAR_CELL_SIZE);
+    let bottom = SCREEN_HEIGHT - HOTBAR_HEIGHT - HOTBAR_CELL_SPACING - row * (HOTBAR_CELL_SPACING + HOTBAR_CELL_SIZE);
+    Rectangle::new(
+        Vector2::new(right - HOTBAR_CELL_SIZE, bottom - HOTBAR_CELL_SIZE),
+        Vector2::new(right, bottom),
+    )
+}
+
+/// Save and jump actions of every bookmark, listed in the help with their own keys.
+const SAVE_BOOKMARKS: [Action; BOOKMARK_COUNT] = bookmark_actions(true);
+const JUMP_TO_BOOKMARKS: [Action; BOOKMARK_COUNT] = bookmark_actions(false);
+
+const fn bookmark_actions(save: bool) -> [Action; BOOKMARK_COUNT] {
+    let mut actions = [Action::Quit; BOOKMARK_COUNT];
+    let mut i = 0;
+    while i < BOOKMARK_COUNT {
+        actions[i] = if save { Action::SaveBookmark(i) } else { Action::JumpToBookmark(i) };
+        i += 1;
+    }
+    actions
+}
+
+/// Lines of the help, made of the actions with what they do.
+/// Lines without a text are shown as they are.
+const HELP: &[(&[Action], &str)] = &[
+    (&[Action::MoveUp, Action::MoveLeft, Action::MoveDown, Action::MoveRight], "to move"),
+    (&[Action::ZoomOut, Action::ZoomIn], "to zoom"),
+    (&[], "Left click to place"),
+    (&[], "Right click to delete"),
+    (&[Action::ShrinkTool, Action::GrowTool], "to change cursor size"),
+    (&[Action::RotateLeft, Action::RotateRight], "to rotate"),
+    (&[Action::Import, Action::ExportQ1, Action::ExportQ2], "to import/export"),
+    (&[Action::Start], "to start"),
+    (&[Action::Step], "to step"),
+    (&[Action::Reset], "to reset"),
+    (&[Action::Stats], "to show statistics"),
+    (&[Action::Profile], "to show subtick times"),
+    (&[Action::UpdateOrder], "to change update order"),
+    (&[Action::Threaded], "to toggle multithreading"),
+    (&[Action::CheckLoop], "to detect loops"),
+    (&[Action::PlayerUp, Action::PlayerLeft, Action::PlayerDown, Action::PlayerRight], "to move players"),
+    (&[Action::PuzzleEditor], "to toggle the puzzle editor"),
+    (&[Action::RemovePuzzle], "to remove the puzzle"),
+    (&[Action::AddItem, Action::RemoveItem], "to change the puzzle inventory"),
+    (&[Action::Levels], "to browse levels"),
+    (&[Action::Confirm], "to play the next level"),
+    (&[Action::Blueprints], "to open blueprints"),
+    (&[Action::SaveBlueprint], "to save the selection"),
+    (&[Action::Find], "to find and replace"),
+    (&[Action::Symmetry], "to change the symmetry"),
+    (&[Action::SymmetryCenter], "to move the symmetry center"),
+    (&[Action::Inspector], "to inspect cells"),
+    (&[Action::SubtickMode], "to step by subtick"),
+    (&[Action::SmoothMovement], "to toggle smooth movement"),
+    (&[Action::Undo], "to undo"),
+    (&[Action::Heatmap], "to show the activity heatmap"),
+    (&[Action::ExportImage], "to export the grid or selection as an image"),
+    (&[Action::Record], "to record the grid or selection"),
+    (&[], "Click or drag the minimap to move around"),
+    (&[], "Middle mouse button to pan, scroll to zoom at the cursor"),
+    (&[Action::FitGrid], "to fit the grid"),
+    (&[Action::CenterSelection], "to center the selection"),
+    (&SAVE_BOOKMARKS, "to save camera bookmarks"),
+    (&JUMP_TO_BOOKMARKS, "to jump to a camera bookmark"),
+    (&[Action::Quit], "to quit"),
+];
+
+/// The help with the keys of the keymap. Actions without keys are left out.
+/// Joins the keys of each action with what they do, actions without keys are left out.
+fn key_hints(settings: &Settings, hints: &[(Action, &str)]) -> String {
+    hints.iter()
+        .map(|&(action, text)| (settings.keymap.describe(action), text))
+        .filter(|(keys, _)| !keys.is_empty())
+        .map(|(keys, text)| format!("{keys} {text}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn help_text(settings: &Settings) -> String {
+    let keys = |actions: &[Action]| actions.iter()
+        .map(|&action| settings.keymap.describe(action))
+        .filter(|keys| !keys.is_empty())
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let mut lines = Vec::new();
+    for &(actions, text) in HELP {
+        if actions.is_empty() {
+            lines.push(text.to_string());
+        }
+        else {
+            let keys = keys(actions);
+            if !keys.is_empty() {
+                lines.push(format!("{keys} {text}"));
+            }
+        }
+    }
+    if let Some(path) = &settings.path {
+        lines.push(format!("Keys can be changed in {}", path.display()));
+    }
+    lines.push(String::new());
+    lines.push(format!("Press {} to hide this message", keys(&[Action::Close])));
+    lines.join("\n")
+}
+
+/// Zooms by a factor, keeping the grid position under the point at the same place on the screen.
+unsafe fn zoom_at(factor: f32, point: Vector2<f32>) {
+    let offset_x = (point.x - SCREEN_WIDTH / 2.0) / CELL_SIZE;
+    let offset_y = (point.y - SCREEN_HEIGHT / 2.0) / CELL_SIZE;
+    let (x, y) = (screen_x + offset_x / screen_zoom, screen_y - offset_y / screen_zoom);
+    screen_zoom *= factor;
+    screen_x = x - offset_x / screen_zoom;
+    screen_y = y + offset_y / screen_zoom;
+}
+
+/// Zooms and moves the camera so the whole grid is visible above the hotbar.
+unsafe fn fit_grid() {
+    let height = SCREEN_HEIGHT - HOTBAR_HEIGHT;
+    screen_zoom = (SCREEN_WIDTH / (grid.width as f32 * CELL_SIZE)).min(height / (grid.height as f32 * CELL_SIZE)) * 0.95;
+    screen_x = grid.width as f32 / 2.0;
+    // the center of the visible area is above the center of the screen
+    screen_y = grid.height as f32 / 2.0 - HOTBAR_HEIGHT / 2.0 / CELL_SIZE / screen_zoom;
+}
+
+/// Position of the minimap in the bottom left corner, keeping the aspect ratio of the grid.
+unsafe fn minimap_rect() -> Rectangle {
+    let scale = MINIMAP_SIZE / grid.width.max(grid.height) as f32;
+    let bottom = SCREEN_HEIGHT - HOTBAR_HEIGHT - MINIMAP_MARGIN;
+    Rectangle::new(
+        Vector2::new(MINIMAP_MARGIN, bottom - grid.height as f32 * scale),
+        Vector2::new(MINIMAP_MARGIN + grid.width as f32 * scale, bottom),
+    )
+}
+
+/// Grid position shown at a point on the minimap.
+unsafe fn minimap_to_grid(point: Vector2<f32>) -> (f32, f32) {
+    let rect = minimap_rect();
+    let scale = rect.width() / grid.width as f32;
+    (
+        ((point.x - rect.top_left().x) / scale).clamp(0.0, grid.width as f32),
+        ((rect.bottom_right().y - point.y) / scale).clamp(0.0, grid.height as f32),
+    )
+}
+
+/// Draws the minimap with the part of the grid visible on the screen.
+unsafe fn draw_minimap(g: &mut Graphics2D, image: &ImageHandle) {
+    let rect = minimap_rect();
+    g.draw_rectangle_image(rect.clone(), image);
+    draw_stroke_rect(g, rect.clone(), Color::from_hex_rgb(0xaaaaaa), 1.0);
+
+    let scale = rect.width() / grid.width as f32;
+    let half_w = SCREEN_WIDTH / 2.0 / CELL_SIZE / screen_zoom;
+    let half_h = SCREEN_HEIGHT / 2.0 / CELL_SIZE / screen_zoom;
+    let (left, bottom) = (rect.top_left().x, rect.bottom_right().y);
+    let to_minimap = |x: f32, y: f32| Vector2::new(
+        (left + x * scale).clamp(rect.top_left().x, rect.bottom_right().x),
+        (bottom - y * scale).clamp(rect.top_left().y, rect.bottom_right().y),
+    );
+    let viewport = Rectangle::new(
+        to_minimap(screen_x - half_w, screen_y + half_h),
+        to_minimap(screen_x + half_w, screen_y - half_h),
+    );
+    draw_stroke_rect(g, viewport, Color::WHITE, 1.0);
+}
+
+/// Screen rectangle around the cells between two corners.
+unsafe fn region_rect(a: (isize, isize), b: (isize, isize)) -> Rectangle {
+    let screen_w_half = SCREEN_WIDTH / 2.0;
+    let screen_h_half = SCREEN_HEIGHT / 2.0;
+    let (min_x, max_x) = (a.0.min(b.0) as f32, a.0.max(b.0) as f32 + 1.0);
+    let (min_y, max_y) = (a.1.min(b.1) as f32, a.1.max(b.1) as f32 + 1.0);
+    Rectangle::new(
+        Vector2::new((min_x - screen_x) * CELL_SIZE * screen_zoom + screen_w_half, (screen_y - max_y) * CELL_SIZE * screen_zoom + screen_h_half),
+        Vector2::new((max_x - screen_x) * CELL_SIZE * screen_zoom + screen_w_half, (screen_y - min_y) * CELL_SIZE * screen_zoom + screen_h_half),
+    )
+}
+
+/// Draws the mirror axes or the rotation center of a symmetry.
+unsafe fn draw_symmetry_axes(g: &mut Graphics2D, symmetry: Symmetry, center: (isize, isize)) {
+    let x = (center.0 as f32 / 2.0 - screen_x) * CELL_SIZE * screen_zoom + SCREEN_WIDTH / 2.0;
+    let y = (screen_y - center.1 as f32 / 2.0) * CELL_SIZE * screen_zoom + SCREEN_HEIGHT / 2.0;
+    let color = Color::from_hex_argb(0xa0ff00ff);
+    if matches!(symmetry, Symmetry::Horizontal | Symmetry::FourWay) {
+        g.draw_line(Vector2::new(x, 0.0), Vector2::new(x, SCREEN_HEIGHT), 2.0, color);
+    }
+    if matches!(symmetry, Symmetry::Vertical | Symmetry::FourWay) {
+        g.draw_line(Vector2::new(0.0, y), Vector2::new(SCREEN_WIDTH, y), 2.0, color);
+    }
+    if symmetry == Symmetry::Rotational {
+        g.draw_circle(Vector2::new(x, y), CELL_SIZE * screen_zoom / 4.0, color);
+    }
+}
+
+/// Lowest corner of a blueprint centered on the position.
+fn blueprint_origin(blueprint: &Grid, pos: (isize, isize)) -> (isize, isize) {
+    (pos.0 - blueprint.width as isize / 2, pos.1 - blueprint.height as isize / 2)
+}
+
+/// Tools that place cells between the press and release position.
+fn is_drag_tool(tool: Tool) -> bool {
+    matches!(tool, Tool::Line(_) | Tool::DragRect(_) | Tool::DragEllipse(_) | Tool::Select)
+}
+
+/// Positions covered by a drag tool when dragged from `start` to `end`.
+fn drag_cells(tool: Tool, start: (isize, isize), end: (isize, isize)) -> Vec<(isize, isize)> {
+    let (min_x, max_x) = (start.0.min(end.0), start.0.max(end.0));
+    let (min_y, max_y) = (start.1.min(end.1), start.1.max(end.1));
+    let inside: Box<dyn Fn(isize, isize) -> bool> = match tool {
+        Tool::Line(_) => return line_cells(start, end),
+        Tool::DragRect(_) => Box::new(|x, y| x >= min_x && x <= max_x && y >= min_y && y <= max_y),
+        Tool::DragEllipse(_) => {
+            let center_x = (min_x + max_x + 1) as f32 / 2.0;
+            let center_y = (min_y + max_y + 1) as f32 / 2.0;
+            let radius_x = (max_x - min_x + 1) as f32 / 2.0;
+            let radius_y = (max_y - min_y + 1) as f32 / 2.0;
+            Box::new(move |x, y| {
+                let dx = (x as f32 + 0.5 - center_x) / radius_x;
+                let dy = (y as f32 + 0.5 - center_y) / radius_y;
+                dx * dx + dy * dy <= 1.0
+            })
+        },
+        _ => return Vec::new(),
+    };
+    let filled = matches!(tool, Tool::DragRect(true) | Tool::DragEllipse(true));
+
+    let mut cells = Vec::new();
+    for y in min_y..=max_y {
+        for x in min_x..=max_x {
+            // outlines only contain positions next to the outside
+            if inside(x, y) && (filled || !(inside(x + 1, y) && inside(x - 1, y) && inside(x, y + 1) && inside(x, y - 1))) {
+                cells.push((x, y));
+            }
+        }
+    }
+    cells
+}
+
+/// Grid position of the cell under the mouse.
+unsafe fn mouse_cell(mouse_pos: Vector2<f32>) -> (isize, isize) {
+    let screen_w_half = SCREEN_WIDTH / 2.0;
+    let screen_h_half = SCREEN_HEIGHT / 2.0;
+    let x = (mouse_pos.x - screen_w_half) / CELL_SIZE / screen_zoom + screen_x;
+    let y = screen_y - (mouse_pos.y - screen_h_half) / CELL_SIZE / screen_zoom;
+    (x.floor() as isize, y.floor() as isize)
+}
+
+/// Positions connected to the start position that look like the cell there, or are empty if it is empty.
+fn fill_area(fill_grid: &Grid, x: isize, y: isize) -> Vec<(isize, isize)> {
+    if !fill_grid.is_in_bounds(x, y) { return Vec::new(); }
+
+    let start = fill_grid.get(x, y).clone();
+    let matches = |cell: &Option<Cell>| match (cell, &start) {
+        (Some(cell), Some(start)) => cell.looks_like(start),
+        (None, None) => true,
+        _ => false,
+    };
+
+    let mut visited = HashSet::from([(x, y)]);
+    let mut queue = VecDeque::from([(x, y)]);
+    let mut area = Vec::new();
+    while let Some((x, y)) = queue.pop_front() {
+        area.push((x, y));
+        for dir in [Direction::Right, Direction::Down, Direction::Left, Direction::Up] {
+            let offset = dir.to_vector();
+            let next = (x + offset.x, y + offset.y);
+            if fill_grid.is_in_bounds(next.0, next.1) && matches(fill_grid.get(next.0, next.1)) && visited.insert(next) {
+                queue.push_back(next);
+            }
+        }
     }
+    area
 }
 
-unsafe fn draw_grid(assets: &Assets, g: &mut Graphics2D) {
-    // calculate visible cells
+/// Positions on the line between two positions, using Bresenham's algorithm.
+fn line_cells(start: (isize, isize), end: (isize, isize)) -> Vec<(isize, isize)> {
+    let dx = (end.0 - start.0).abs();
+    let dy = -(end.1 - start.1).abs();
+    let sx = (end.0 - start.0).signum();
+    let sy = (end.1 - start.1).signum();
+    let (mut x, mut y) = start;
+    let mut error = dx + dy;
+    let mut cells = vec![start];
+    while (x, y) != end {
+        let e2 = 2 * error;
+        if e2 >= dy {
+            error += dy;
+            x += sx;
+        }
+        if e2 <= dx {
+            error += dx;
+            y += sy;
+        }
+        cells.push((x, y));
+    }
+    cells
+}
+
+/// Cell placed by the line tool, pointing along the main axis of the line from `start` to `end` if `orient` is set.
+fn line_cell(cell: &Cell, start: (isize, isize), end: (isize, isize), orient: bool) -> Cell {
+    let mut cell = cell.clone();
+    let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+    if orient && (dx, dy) != (0, 0) {
+        cell.set_direction(match (dx.abs() >= dy.abs(), dx > 0, dy > 0) {
+            (true, true, _) => Direction::Right,
+            (true, false, _) => Direction::Left,
+            (false, _, true) => Direction::Up,
+            (false, _, false) => Direction::Down,
+        });
+    }
+    cell
+}
+
+/// Range of grid positions visible on the screen, as the lowest corner and the highest corner (exclusive).
+unsafe fn visible_cells() -> ((isize, isize), (isize, isize)) {
     let screen_w_half = SCREEN_WIDTH / 2.0;
     let screen_h_half = SCREEN_HEIGHT / 2.0;
     let sx = (-screen_w_half) / CELL_SIZE / screen_zoom + screen_x;
@@ -827,47 +2268,529 @@ unsafe fn draw_grid(assets: &Assets, g: &mut Graphics2D) {
     let sy = (sy.floor() as isize).max(0).min(grid.height as isize);
     let ex = (ex.ceil() as isize).max(0).min(grid.width as isize);
     let ey = (ey.ceil() as isize).max(0).min(grid.height as isize);
+    ((sx, sy), (ex, ey))
+}
 
+/// Colors the visible positions by how often they changed, from blue for rarely to red for every tick.
+unsafe fn draw_heatmap(g: &mut Graphics2D, activity: &Activity) {
+    let ((sx, sy), (ex, ey)) = visible_cells();
     for y in sy..ey {
         for x in sx..ex {
-            let cell_rect = Rectangle::new(
-                Vector2::new(
-                    (x as f32 - screen_x) * CELL_SIZE * screen_zoom + screen_w_half,
-                    (screen_y - y as f32 - 1.0) * CELL_SIZE * screen_zoom + screen_h_half,
-                ),
-                Vector2::new(
-                    (x as f32 - screen_x + 1.0) * CELL_SIZE * screen_zoom + screen_w_half,
-                    (screen_y - y as f32) * CELL_SIZE * screen_zoom + screen_h_half,
-                )
-            );
+            let heat = activity.heat(y as usize * grid.width + x as usize);
+            if heat > 0.0 {
+                g.draw_rectangle(region_rect((x, y), (x, y)), Color::from_rgba(heat, 0.2, 1.0 - heat, 0.6));
+            }
+        }
+    }
+}
 
-            if let Some(cell) = grid.get_unchecked(x, y) {
-                // draw cell
-                g.draw_rectangle_image(cell_rect, &assets.cells.get(&cell.id()).unwrap()[usize::from(cell.direction())]);
-                // if cell.id == MAILBOX {
-                //     if let Some((id, dir)) = cell.contained_cell {
-                //         let cell_rect = Rectangle::new(
-                //             Vector2::new(
-                //                 ((x as f32 - screen_x) * CELL_SIZE + CELL_SIZE / 4.0) * screen_zoom + screen_w_half,
-                //                 ((screen_y - y as f32 - 1.0) * CELL_SIZE + CELL_SIZE / 4.0) * screen_zoom + screen_h_half,
-                //             ),
-                //             Vector2::new(
-                //                 ((x as f32 - screen_x + 1.0) * CELL_SIZE - CELL_SIZE / 4.0) * screen_zoom + screen_w_half,
-                //                 ((screen_y - y as f32) * CELL_SIZE - CELL_SIZE / 4.0) * screen_zoom + screen_h_half,
-                //             )
-                //         );
-                //         g.draw_rectangle_image(cell_rect, &assets.cells.get(&id).unwrap()[usize::from(cell.direction + dir)]);
-                //     }
-                // }
+/// Draws the visible part of the grid zoomed out, as a single texture with a color per cell,
+/// or as chunk textures that are only rendered again when their cells change.
+unsafe fn draw_grid_lod(assets: &Assets, g: &mut Graphics2D, chunks: &mut ChunkCache, color_cache: &mut ColorCache, highlight: Option<&CellQuery>, colors_only: bool) {
+    let ((sx, sy), (ex, ey)) = visible_cells();
+    if sx < ex && sy < ey {
+        if colors_only {
+            // zoomed out far enough, the visible cells are sampled down to the screen size
+            let screen_size = (SCREEN_WIDTH.ceil() as u32, SCREEN_HEIGHT.ceil() as u32);
+            let image = color_cache.get(g, &grid, &assets.cell_colors, (sx, sy), (ex - 1, ey - 1), screen_size);
+            g.draw_rectangle_image(region_rect((sx, sy), (ex - 1, ey - 1)), image);
+        }
+        else {
+            for cy in sy.div_euclid(CHUNK_SIZE)..=(ey - 1).div_euclid(CHUNK_SIZE) {
+                for cx in sx.div_euclid(CHUNK_SIZE)..=(ex - 1).div_euclid(CHUNK_SIZE) {
+                    let min = (cx * CHUNK_SIZE, cy * CHUNK_SIZE);
+                    let image = chunks.get(g, &grid, &assets.chunk_textures, (cx, cy));
+                    g.draw_rectangle_image(region_rect(min, (min.0 + CHUNK_SIZE - 1, min.1 + CHUNK_SIZE - 1)), image);
+                }
+            }
+        }
+    }
+    chunks.evict_unused();
+
+    if highlight.is_none() && grid.puzzle.is_none() { return; }
+    for y in sy..ey {
+        for x in sx..ex {
+            if let (Some(query), Some(cell)) = (highlight, grid.get_unchecked(x, y)) {
+                if query.matches(x, y, cell) {
+                    g.draw_rectangle(region_rect((x, y), (x, y)), Color::from_hex_argb(0x50ffff00));
+                }
+            }
+            if grid.puzzle.as_ref().is_some_and(|puzzle| puzzle.in_zone(x, y)) {
+                g.draw_rectangle(region_rect((x, y), (x, y)), Color::from_hex_argb(0x4000ff40));
+            }
+        }
+    }
+}
+
+/// Draws the visible part of the grid, highlighting cells matched by the query.
+/// Moved cells are drawn between their old and new position if the progress of the animation is given.
+/// Cells are drawn with less detail and without animations when zoomed out, see `draw_grid_lod`.
+unsafe fn draw_grid(assets: &Assets, g: &mut Graphics2D, chunks: &mut ChunkCache, color_cache: &mut ColorCache, highlight: Option<&CellQuery>, progress: Option<f32>) {
+    let cell_pixels = CELL_SIZE * screen_zoom;
+    if cell_pixels < LOD_CHUNK_PIXELS {
+        draw_grid_lod(assets, g, chunks, color_cache, highlight, cell_pixels < LOD_COLOR_PIXELS);
+        return;
+    }
+    // no chunks are drawn when zoomed in
+    chunks.evict_unused();
+
+    let screen_w_half = SCREEN_WIDTH / 2.0;
+    let screen_h_half = SCREEN_HEIGHT / 2.0;
+    let ((sx, sy), (ex, ey)) = visible_cells();
+
+    let cell_rect = |x: f32, y: f32| Rectangle::new(
+        Vector2::new(
+            (x - screen_x) * CELL_SIZE * screen_zoom + screen_w_half,
+            (screen_y - y - 1.0) * CELL_SIZE * screen_zoom + screen_h_half,
+        ),
+        Vector2::new(
+            (x - screen_x + 1.0) * CELL_SIZE * screen_zoom + screen_w_half,
+            (screen_y - y) * CELL_SIZE * screen_zoom + screen_h_half,
+        )
+    );
+
+    for y in sy..ey {
+        for x in sx..ex {
+            let cell_rect = cell_rect(x as f32, y as f32);
+
+            match grid.get_unchecked(x, y) {
+                Some(cell) if progress.is_none() => {
+                    // draw cell
+                    g.draw_rectangle_image(cell_rect.clone(), &assets.cells.get(&cell.id()).unwrap()[usize::from(cell.direction())]);
+                    if highlight.is_some_and(|query| query.matches(x, y, cell)) {
+                        g.draw_rectangle(cell_rect.clone(), Color::from_hex_argb(0x50ffff00));
+                    }
+                    // if cell.id == MAILBOX {
+                    //     if let Some((id, dir)) = cell.contained_cell {
+                    //         let cell_rect = Rectangle::new(
+                    //             Vector2::new(
+                    //                 ((x as f32 - screen_x) * CELL_SIZE + CELL_SIZE / 4.0) * screen_zoom + screen_w_half,
+                    //                 ((screen_y - y as f32 - 1.0) * CELL_SIZE + CELL_SIZE / 4.0) * screen_zoom + screen_h_half,
+                    //             ),
+                    //             Vector2::new(
+                    //                 ((x as f32 - screen_x + 1.0) * CELL_SIZE - CELL_SIZE / 4.0) * screen_zoom + screen_w_half,
+                    //                 ((screen_y - y as f32) * CELL_SIZE - CELL_SIZE / 4.0) * screen_zoom + screen_h_half,
+                    //             )
+                    //         );
+                    //         g.draw_rectangle_image(cell_rect, &assets.cells.get(&id).unwrap()[usize::from(cell.direction + dir)]);
+                    //     }
+                    // }
+                },
+                _ => {
+                    // draw background, animated cells are drawn over it afterwards
+                    g.draw_rectangle_image(cell_rect.clone(), &assets.cell_bg);
+                },
+            }
+
+            // puzzle build zone
+            if progress.is_none() && grid.puzzle.as_ref().is_some_and(|puzzle| puzzle.in_zone(x, y)) {
+                g.draw_rectangle(cell_rect, Color::from_hex_argb(0x4000ff40));
+            }
+        }
+    }
+
+    let Some(progress) = progress else { return };
+    let remaining = 1.0 - progress.clamp(0.0, 1.0);
+    let motion = grid.motion.as_deref().unwrap_or_default();
+
+    // cells moving in from outside of the screen
+    let (sx, sy) = ((sx - 1).max(0), (sy - 1).max(0));
+    let (ex, ey) = ((ex + 1).min(grid.width as isize), (ey + 1).min(grid.height as isize));
+    for y in sy..ey {
+        for x in sx..ex {
+            let Some(cell) = grid.get_unchecked(x, y) else { continue };
+            let cell_motion = motion.get(y as usize * grid.width + x as usize).copied().unwrap_or_default();
+
+            let rect = cell_rect(
+                x as f32 - cell_motion.dx as f32 * remaining,
+                y as f32 - cell_motion.dy as f32 * remaining,
+            );
+            let image = &assets.cells.get(&cell.id()).unwrap()[usize::from(cell.direction())];
+            if cell_motion.turns == 0 {
+                g.draw_rectangle_image(rect.clone(), image);
             }
             else {
-                // draw background
-                g.draw_rectangle_image(cell_rect.clone(), &assets.cell_bg);
+                let angle = -(cell_motion.turns as f32) * remaining * std::f32::consts::FRAC_PI_2;
+                draw_rotated_image(g, &rect, angle, image);
             }
+            if highlight.is_some_and(|query| query.matches(x, y, cell)) {
+                g.draw_rectangle(rect, Color::from_hex_argb(0x50ffff00));
+            }
+        }
+    }
+
+    if let Some(puzzle) = &grid.puzzle {
+        for y in sy..ey {
+            for x in sx..ex {
+                if puzzle.in_zone(x, y) {
+                    g.draw_rectangle(cell_rect(x as f32, y as f32), Color::from_hex_argb(0x4000ff40));
+                }
+            }
+        }
+    }
+}
+
+/// Draws an image rotated clockwise around the center of the rectangle by an angle in radians.
+fn draw_rotated_image(g: &mut Graphics2D, rect: &Rectangle, angle: f32, image: &ImageHandle) {
+    let center = (*rect.top_left() + *rect.bottom_right()) / 2.0;
+    let (sin, cos) = angle.sin_cos();
+    let corner = |x: f32, y: f32| {
+        let offset = Vector2::new(x * rect.width() / 2.0, y * rect.height() / 2.0);
+        center + Vector2::new(offset.x * cos - offset.y * sin, offset.x * sin + offset.y * cos)
+    };
+    g.draw_quad_image_tinted_four_color(
+        [corner(-1.0, -1.0), corner(1.0, -1.0), corner(1.0, 1.0), corner(-1.0, 1.0)],
+        [Color::WHITE; 4],
+        [Vector2::new(0.0, 0.0), Vector2::new(1.0, 0.0), Vector2::new(1.0, 1.0), Vector2::new(0.0, 1.0)],
+        image,
+    );
+}
+
+/// Position of a level thumbnail in the level browser.
+unsafe fn level_rect(index: usize, scroll: f32) -> Rectangle {
+    let columns = ((SCREEN_WIDTH - LEVEL_SPACING) / (LEVEL_THUMBNAIL_SIZE + LEVEL_SPACING)).max(1.0) as usize;
+    let x = LEVEL_SPACING + (index % columns) as f32 * (LEVEL_THUMBNAIL_SIZE + LEVEL_SPACING);
+    let y = LEVEL_TOP + (index / columns) as f32 * (LEVEL_THUMBNAIL_SIZE + LEVEL_SPACING * 1.5) - scroll;
+    Rectangle::new(Vector2::new(x, y), Vector2::new(x + LEVEL_THUMBNAIL_SIZE, y + LEVEL_THUMBNAIL_SIZE))
+}
+
+unsafe fn draw_level_browser(assets: &Assets, g: &mut Graphics2D, settings: &Settings, levels: &LevelPack, current: Option<usize>, scroll: f32) {
+    g.draw_rectangle(
+        Rectangle::new(Vector2::new(0.0, 0.0), Vector2::new(SCREEN_WIDTH, SCREEN_HEIGHT)),
+        Color::from_hex_argb(0xf0222222),
+    );
+
+    let title = if levels.levels.is_empty() {
+        "No levels found in the levels folder".to_string()
+    }
+    else {
+        let hints = key_hints(settings, &[(Action::Levels, "to close")]);
+        let hints = if hints.is_empty() { "click to play".to_string() } else { format!("click to play, {hints}") };
+        format!("Levels ({}/{} solved) - {hints}", levels.solved_count(), levels.levels.len())
+    };
+    g.draw_text(
+        Vector2::new(LEVEL_SPACING, LEVEL_SPACING - scroll),
+        Color::WHITE,
+        &assets.font.layout_text(&title, 25.0, TextOptions::new()),
+    );
+
+    for (i, level) in levels.levels.iter().enumerate() {
+        let rect = level_rect(i, scroll);
+        if rect.bottom_right().y < 0.0 || rect.top_left().y > SCREEN_HEIGHT { continue; }
+
+        // keep the aspect ratio of the grid
+        let scale = LEVEL_THUMBNAIL_SIZE / level.grid.width.max(level.grid.height) as f32;
+        let size = Vector2::new(level.grid.width as f32 * scale, level.grid.height as f32 * scale);
+        let top_left = *rect.top_left() + (Vector2::new(LEVEL_THUMBNAIL_SIZE, LEVEL_THUMBNAIL_SIZE) - size) / 2.0;
+        g.draw_rectangle_image(Rectangle::new(top_left, top_left + size), &assets.thumbnails[i]);
+
+        let border = if levels.is_solved(i) { Color::GREEN } else if current == Some(i) { Color::WHITE } else { Color::DARK_GRAY };
+        let corners = [*rect.top_left(), rect.top_right(), *rect.bottom_right(), rect.bottom_left()];
+        for c in 0..4 {
+            g.draw_line(corners[c], corners[(c + 1) % 4], 2.0, border);
         }
+
+        let name = if levels.is_solved(i) { format!("{} (solved)", level.name) } else { level.name.clone() };
+        g.draw_text(
+            rect.bottom_left() + Vector2::new(0.0, 5.0),
+            Color::WHITE,
+            &assets.font.layout_text(&name, 17.0, TextOptions::new().with_wrap_to_width(LEVEL_THUMBNAIL_SIZE + LEVEL_SPACING, TextAlignment::Left)),
+        );
     }
 }
 
+fn next_direction(direction: Option<Direction>) -> Option<Direction> {
+    match direction {
+        None => Some(Direction::Right),
+        Some(Direction::Up) => None,
+        Some(direction) => Some(direction + 1),
+    }
+}
+
+fn direction_name(direction: Option<Direction>, none: &str) -> String {
+    direction.map_or(none.to_string(), |direction| direction.to_string())
+}
+
+/// Position of a row in the find and replace dialog.
+fn find_row_rect(row: usize) -> Rectangle {
+    let width = unsafe { SCREEN_WIDTH };
+    let top = FIND_ROW_HEIGHT * row as f32;
+    Rectangle::new(Vector2::new(width - FIND_PANEL_WIDTH, top), Vector2::new(width, top + FIND_ROW_HEIGHT))
+}
+
+unsafe fn draw_find_dialog(assets: &Assets, g: &mut Graphics2D, (query, replacement): &(CellQuery, Replacement), count: usize, mouse_pos: Vector2<f32>) {
+    g.draw_rectangle(
+        Rectangle::new(*find_row_rect(0).top_left(), *find_row_rect(FIND_ROWS - 1).bottom_right()),
+        Color::from_hex_argb(0xf0222222),
+    );
+
+    let rows = [
+        (format!("Find: {}", cell_data(query.id).name), Some(query.id)),
+        (format!("Direction: {}", direction_name(query.direction, "Any")), None),
+        (format!("Region: {}", if query.region.is_some() { "Selection" } else { "Whole grid" }), None),
+        match replacement {
+            Replacement::Change(Some(id), _) => (format!("Replace with: {}", cell_data(*id).name), Some(*id)),
+            Replacement::Change(None, _) => ("Replace with: Same type".to_string(), None),
+            Replacement::Delete => ("Replace with: Nothing".to_string(), None),
+        },
+        match replacement {
+            Replacement::Change(_, direction) => (format!("New direction: {}", direction_name(*direction, "Keep")), None),
+            Replacement::Delete => ("New direction: -".to_string(), None),
+        },
+        if replacement.changes(query) {
+            (format!("Replace {count} matches (Enter)"), None)
+        }
+        else {
+            (format!("{count} matches, choose a replacement"), None)
+        },
+    ];
+    for (row, (text, id)) in rows.iter().enumerate() {
+        let rect = find_row_rect(row);
+        if is_inside(rect.clone(), mouse_pos) {
+            g.draw_rectangle(rect.clone(), Color::from_hex_argb(0x30ffffff));
+        }
+        let text = assets.font.layout_text(text, 17.0, TextOptions::new());
+        g.draw_text(rect.top_left() + Vector2::new(10.0, (FIND_ROW_HEIGHT - text.height()) / 2.0), Color::WHITE, &text);
+        if let Some(id) = id {
+            let size = FIND_ROW_HEIGHT - 8.0;
+            let top_left = rect.top_right() + Vector2::new(-size - 4.0, 4.0);
+            g.draw_rectangle_image(Rectangle::new(top_left, top_left + Vector2::new(size, size)), &assets.cells.get(id).unwrap()[0]);
+        }
+    }
+}
+
+/// Position of a row in the recording panel, below the find and replace dialog if it is open.
+fn record_row_rect(row: usize, below_find: bool) -> Rectangle {
+    let width = unsafe { SCREEN_WIDTH };
+    let top = if below_find { FIND_ROW_HEIGHT * FIND_ROWS as f32 } else { 0.0 } + FIND_ROW_HEIGHT * row as f32;
+    Rectangle::new(Vector2::new(width - RECORD_PANEL_WIDTH, top), Vector2::new(width, top + FIND_ROW_HEIGHT))
+}
+
+fn draw_record_panel(assets: &Assets, g: &mut Graphics2D, recording: &Recording, below_find: bool, mouse_pos: Vector2<f32>) {
+    g.draw_rectangle(
+        Rectangle::new(*record_row_rect(0, below_find).top_left(), *record_row_rect(RECORD_ROWS - 1, below_find).bottom_right()),
+        Color::from_hex_argb(0xf0222222),
+    );
+
+    let rows = [
+        format!("Recording: {} frames", recording.frame_count()),
+        format!("Frame skip: {}", recording.options.frame_skip),
+        format!("Cell size: {} px", recording.options.cell_size),
+    ];
+    for (row, text) in rows.iter().enumerate() {
+        let rect = record_row_rect(row, below_find);
+        if row > 0 && is_inside(rect.clone(), mouse_pos) {
+            g.draw_rectangle(rect.clone(), Color::from_hex_argb(0x30ffffff));
+        }
+        let color = if row == 0 { Color::RED } else { Color::WHITE };
+        let text = assets.font.layout_text(text, 17.0, TextOptions::new());
+        g.draw_text(rect.top_left() + Vector2::new(10.0, (FIND_ROW_HEIGHT - text.height()) / 2.0), color, &text);
+    }
+}
+
+/// Amount of rows in the blueprint panel, including the search field.
+unsafe fn blueprint_rows() -> usize {
+    ((SCREEN_HEIGHT - HOTBAR_HEIGHT) / BLUEPRINT_ROW_HEIGHT) as usize
+}
+
+/// Position of a row in the blueprint panel. Row 0 is the search field right above the hotbar.
+unsafe fn blueprint_rect(row: usize) -> Rectangle {
+    let bottom = SCREEN_HEIGHT - HOTBAR_HEIGHT - row as f32 * BLUEPRINT_ROW_HEIGHT;
+    Rectangle::new(Vector2::new(0.0, bottom - BLUEPRINT_ROW_HEIGHT), Vector2::new(BLUEPRINT_PANEL_WIDTH, bottom))
+}
+
+unsafe fn draw_blueprint_panel(assets: &Assets, g: &mut Graphics2D, settings: &Settings, library: &BlueprintLibrary, thumbnails: &HashMap<String, ImageHandle>, search: &str, mouse_pos: Vector2<f32>) {
+    let results = library.search(search);
+    let rows = blueprint_rows().min(results.len() + 1).max(1);
+    g.draw_rectangle(
+        Rectangle::new(*blueprint_rect(rows - 1).top_left(), *blueprint_rect(0).bottom_right()),
+        Color::from_hex_argb(0xf0222222),
+    );
+
+    let search_text = if library.blueprints.is_empty() {
+        match settings.keymap.describe(Action::SaveBlueprint) {
+            keys if keys.is_empty() => "No blueprints".to_string(),
+            keys => format!("No blueprints, select cells and press {keys}"),
+        }
+    }
+    else if search.is_empty() {
+        "Type to search, ESC to close".to_string()
+    }
+    else {
+        format!("Search: {search}_")
+    };
+    let text = assets.font.layout_text(&search_text, 17.0, TextOptions::new());
+    let rect = blueprint_rect(0);
+    g.draw_text(rect.top_left() + Vector2::new(10.0, (BLUEPRINT_ROW_HEIGHT - text.height()) / 2.0), Color::WHITE, &text);
+
+    for (row, &index) in results.iter().take(rows - 1).enumerate() {
+        let blueprint = &library.blueprints[index];
+        let rect = blueprint_rect(row + 1);
+        if is_inside(rect.clone(), mouse_pos) {
+            g.draw_rectangle(rect.clone(), Color::from_hex_argb(0x30ffffff));
+        }
+
+        // keep the aspect ratio of the blueprint
+        let thumbnail_size = BLUEPRINT_ROW_HEIGHT - 10.0;
+        let scale = thumbnail_size / blueprint.grid.width.max(blueprint.grid.height) as f32;
+        let size = Vector2::new(blueprint.grid.width as f32 * scale, blueprint.grid.height as f32 * scale);
+        let top_left = *rect.top_left() + Vector2::new(5.0, 5.0) + (Vector2::new(thumbnail_size, thumbnail_size) - size) / 2.0;
+        if let Some(thumbnail) = thumbnails.get(&blueprint.name) {
+            g.draw_rectangle_image(Rectangle::new(top_left, top_left + size), thumbnail);
+        }
+
+        let text = assets.font.layout_text(&format!("{} ({}x{})", blueprint.name, blueprint.grid.width, blueprint.grid.height), 17.0, TextOptions::new());
+        g.draw_text(rect.top_left() + Vector2::new(BLUEPRINT_ROW_HEIGHT, (BLUEPRINT_ROW_HEIGHT - text.height()) / 2.0), Color::WHITE, &text);
+    }
+}
+
+/// Screen position of the center of a cell.
+unsafe fn cell_center(x: isize, y: isize) -> Vector2<f32> {
+    Vector2::new(
+        (x as f32 + 0.5 - screen_x) * CELL_SIZE * screen_zoom + SCREEN_WIDTH / 2.0,
+        (screen_y - y as f32 - 0.5) * CELL_SIZE * screen_zoom + SCREEN_HEIGHT / 2.0,
+    )
+}
+
+/// Draws the chain of a push with the force at every cell, and the outcome next to the mouse.
+unsafe fn draw_push_preview(assets: &Assets, g: &mut Graphics2D, preview: &PushPreview, dir: Direction, mouse_pos: Vector2<f32>) {
+    let offset = dir.to_vector();
+    let length = CELL_SIZE * screen_zoom * 0.35;
+    let forward = Vector2::new(offset.x as f32 * length, -offset.y as f32 * length);
+    let side = Vector2::new(-forward.y / 2.0, forward.x / 2.0);
+    let color = Color::from_hex_argb(0xe0ffdd00);
+
+    for step in &preview.chain {
+        let center = cell_center(step.x, step.y);
+        let tip = center + forward;
+        g.draw_line(center - forward, tip, 3.0, color);
+        g.draw_line(tip, tip - forward / 2.0 + side, 3.0, color);
+        g.draw_line(tip, tip - forward / 2.0 - side, 3.0, color);
+
+        let text = assets.font.layout_text(&step.force.to_string(), 17.0, TextOptions::new());
+        g.draw_text(center + Vector2::new(length, -length) - Vector2::new(text.width(), 0.0), Color::WHITE, &text);
+    }
+
+    let (text, color) = match preview.stop {
+        PushStop::Empty => ("Moves", Color::GREEN),
+        PushStop::Loop => ("Moves in a loop", Color::GREEN),
+        PushStop::Trash(..) if preview.result == PushResult::Trashed => ("Trashed", Color::from_hex_rgb(0xff9900)),
+        PushStop::Trash(..) => ("Moves into a trash", Color::from_hex_rgb(0xff9900)),
+        PushStop::OutOfBounds => ("Blocked by the border", Color::RED),
+        PushStop::Immovable(..) => ("Blocked by an immovable cell", Color::RED),
+        PushStop::NoForce(..) => ("Not enough force", Color::RED),
+    };
+    let text = assets.font.layout_text(text, 17.0, TextOptions::new());
+    g.draw_rectangle(
+        Rectangle::new(mouse_pos + Vector2::new(10.0, -30.0), mouse_pos + Vector2::new(20.0 + text.width(), -30.0 + text.height())),
+        Color::from_hex_argb(0xcc222222),
+    );
+    g.draw_text(mouse_pos + Vector2::new(15.0, -30.0), color, &text);
+}
+
+/// Shows the properties of a cell next to the mouse.
+unsafe fn draw_inspector(assets: &Assets, g: &mut Graphics2D, x: isize, y: isize, cell: &Cell, mouse_pos: Vector2<f32>) {
+    let data = cell_data(cell.id());
+    let columns = ["Push", "Pull", "Swap", "Trash", "Rotate"];
+    let column_width = (INSPECTOR_WIDTH - INSPECTOR_PADDING * 2.0) / (columns.len() + 1) as f32;
+    let height = INSPECTOR_PADDING * 2.0 + INSPECTOR_LINE_HEIGHT * 7.0;
+
+    // keep the panel on the screen
+    let mut top_left = mouse_pos + Vector2::new(20.0, 20.0);
+    top_left.x = top_left.x.min(SCREEN_WIDTH - INSPECTOR_WIDTH);
+    top_left.y = top_left.y.min(SCREEN_HEIGHT - HOTBAR_HEIGHT - height);
+    g.draw_rectangle(
+        Rectangle::new(top_left, top_left + Vector2::new(INSPECTOR_WIDTH, height)),
+        Color::from_hex_argb(0xee222222),
+    );
+
+    let mut line = |row: usize, column: usize, text: &str, color: Color| {
+        let position = top_left + Vector2::new(
+            INSPECTOR_PADDING + column as f32 * column_width,
+            INSPECTOR_PADDING + row as f32 * INSPECTOR_LINE_HEIGHT,
+        );
+        g.draw_text(position, color, &assets.font.layout_text(text, 17.0, TextOptions::new()));
+    };
+    line(0, 0, &format!("{} (ID {}) at {x}, {y}", data.name, data.id), Color::WHITE);
+    line(1, 0, &format!("Facing {}, {} sides", cell.direction(), data.sides), Color::WHITE);
+    for (column, name) in columns.iter().enumerate() {
+        line(2, column + 1, name, Color::LIGHT_GRAY);
+    }
+    // movement and trash depend on the moving direction, rotation on the side
+    for (row, dir) in [Direction::Right, Direction::Down, Direction::Left, Direction::Up].into_iter().enumerate() {
+        line(row + 3, 0, &dir.to_string(), Color::LIGHT_GRAY);
+        let values = [
+            data.behavior.can_move(cell, dir, MoveForce::Push),
+            data.behavior.can_move(cell, dir, MoveForce::Pull),
+            data.behavior.can_move(cell, dir, MoveForce::Swap),
+            data.behavior.is_trash(cell, dir),
+            data.behavior.can_rotate(cell, dir),
+        ];
+        for (column, value) in values.into_iter().enumerate() {
+            line(row + 3, column + 1, if value { "yes" } else { "no" }, if value { Color::GREEN } else { Color::RED });
+        }
+    }
+}
+
+unsafe fn draw_stats(assets: &Assets, g: &mut Graphics2D, stats: &Statistics) {
+    let panel_rect = Rectangle::new(
+        Vector2::new(SCREEN_WIDTH - STATS_WIDTH, 0.0),
+        Vector2::new(SCREEN_WIDTH, SCREEN_HEIGHT - HOTBAR_HEIGHT),
+    );
+    g.draw_rectangle(
+        panel_rect,
+        Color::from_hex_argb(0xcc333333),
+    );
+
+    let mut lines = vec![
+        format!("Population: {}", stats.population),
+        format!("Created per tick: {}", stats.created_per_tick),
+        format!("Destroyed per tick: {}", stats.destroyed_per_tick),
+        format!("Total created: {}", stats.total_created),
+        format!("Total destroyed: {}", stats.total_destroyed),
+        format!("Enemies killed: {}", stats.enemies_killed),
+        format!("Rotations: {}", stats.rotations),
+        format!("Failed pushes: {}", stats.failed_pushes),
+        String::new(),
+    ];
+    for cell in all_cells() {
+        let count = stats.counts[cell.id as usize];
+        if count > 0 {
+            lines.push(format!("{}: {}", cell.name, count));
+        }
+    }
+
+    let x = SCREEN_WIDTH - STATS_WIDTH + STATS_PADDING;
+    let mut y = STATS_PADDING;
+    for line in lines {
+        g.draw_text(
+            Vector2::new(x, y),
+            Color::WHITE,
+            &assets.font.layout_text(&line, 17.0, TextOptions::new()),
+        );
+        y += 20.0;
+    }
+
+    // population graph
+    let graph_bottom = SCREEN_HEIGHT - HOTBAR_HEIGHT - STATS_PADDING;
+    let graph_width = STATS_WIDTH - STATS_PADDING * 2.0;
+    let max = stats.history.iter().copied().max().unwrap_or(0).max(1) as f32;
+    let step = graph_width / (HISTORY_LENGTH - 1) as f32;
+    let point = |i: usize, population: usize| Vector2::new(
+        x + i as f32 * step,
+        graph_bottom - population as f32 / max * STATS_GRAPH_HEIGHT,
+    );
+    for (i, (a, b)) in stats.history.iter().zip(stats.history.iter().skip(1)).enumerate() {
+        g.draw_line(point(i, *a), point(i + 1, *b), 2.0, Color::GREEN);
+    }
+    g.draw_line(
+        Vector2::new(x, graph_bottom),
+        Vector2::new(x + graph_width, graph_bottom),
+        1.0,
+        Color::LIGHT_GRAY,
+    );
+}
+
 unsafe fn draw_ghost_cell(assets: &Assets, g: &mut Graphics2D, x: isize, y: isize, cell: &Cell) {
     let screen_w_half = SCREEN_WIDTH / 2.0;
     let screen_h_half = SCREEN_HEIGHT / 2.0;
@@ -980,23 +2903,27 @@ struct Assets {
     tool_place: ImageHandle,
     tool_rect: ImageHandle,
     tool_circle: ImageHandle,
+    tool_fill: ImageHandle,
+    tool_line: ImageHandle,
+    tool_line_oriented: ImageHandle,
+    tool_drag_rect: ImageHandle,
+    tool_drag_rect_filled: ImageHandle,
+    tool_drag_ellipse: ImageHandle,
+    tool_drag_ellipse_filled: ImageHandle,
+    tool_select: ImageHandle,
 
     font: Font,
+    /// Thumbnails of the levels in the level pack.
+    thumbnails: Vec<ImageHandle>,
+    /// Average color of every cell type, used for thumbnails.
+    cell_colors: HashMap<CellType, Rgba<u8>>,
+    /// Cell textures for drawing chunks of the grid.
+    chunk_textures: CellTextures,
 }
 
-fn create_rotated_textures(amount: usize, path: PathBuf) -> [ImageBuffer<Rgba<u8>, Vec<u8>>; 4] {
-    let first_texture = image::open(path).unwrap().to_rgba8();
-    let mut textures = [first_texture.clone(), first_texture.clone(), first_texture.clone(), first_texture];
-    for (i, img) in textures.iter_mut().enumerate() {
-        match i % amount {
-            0 => {},
-            1 => *img = rotate90(img),
-            2 => *img = rotate180(img),
-            3 => *img = rotate270(img),
-            _ => unreachable!(),
-        }
-    }
-    textures
+/// Seconds since the Unix epoch, for naming exported files.
+fn unix_time() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs())
 }
 
 fn is_inside<T: PartialOrd>(rect: Rectangle<T>, point: Vector2<T>) -> bool {
@@ -1012,8 +2939,8 @@ impl CellUndoStack {
         CellUndoStack(Vec::new())
     }
 
-    fn start(&mut self) {
-        self.0.push(UndoAction::new());
+    fn start(&mut self, action_grid: &Grid) {
+        self.0.push(UndoAction::new(action_grid.puzzle.as_ref().map(|puzzle| puzzle.inventory.clone())));
         if self.0.len() > UNDO_STACK_SIZE {
             self.0.remove(0);
         }
@@ -1028,20 +2955,24 @@ impl CellUndoStack {
     }
 }
 
-struct UndoAction(HashMap<(isize, isize), Option<Cell>>);
+struct UndoAction(HashMap<(isize, isize), Option<Cell>>, /*inventory*/ Option<BTreeMap<CellType, usize>>);
 
 impl UndoAction {
-    fn new() -> Self {
-        UndoAction(HashMap::new())
+    fn new(inventory: Option<BTreeMap<CellType, usize>>) -> Self {
+        UndoAction(HashMap::new(), inventory)
     }
 
+    /// Saves the cell a position had before the action, later changes of the position are ignored.
     fn insert(&mut self, pos: (isize, isize), cell: Option<Cell>) {
-        self.0.insert(pos, cell);
+        self.0.entry(pos).or_insert(cell);
     }
 
     fn undo_on(&self, action_grid: &mut Grid) {
         for (pos, cell) in self.0.iter() {
             action_grid.set_cell(pos.0, pos.1, cell.clone());
         }
+        if let (Some(puzzle), Some(inventory)) = (&mut action_grid.puzzle, &self.1) {
+            puzzle.inventory = inventory.clone();
+        }
     }
 }
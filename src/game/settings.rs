@@ -0,0 +1,464 @@
+use std::{collections::HashSet, fs, path::PathBuf};
+
+use speedy2d::window::VirtualKeyCode;
+
+use super::cells::{BOOKMARK_COUNT, DEFAULT_GRID_HEIGHT, DEFAULT_GRID_WIDTH, MAX_GRID_SIZE};
+
+/// Something that can be done with a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    MoveUp,
+    MoveLeft,
+    MoveDown,
+    MoveRight,
+    ZoomIn,
+    ZoomOut,
+    GrowTool,
+    ShrinkTool,
+    RotateLeft,
+    RotateRight,
+    Start,
+    Step,
+    SubtickMode,
+    Reset,
+    Import,
+    ExportQ1,
+    ExportQ2,
+    ExportImage,
+    Record,
+    Threaded,
+    CheckLoop,
+    UpdateOrder,
+    Stats,
+    Profile,
+    Heatmap,
+    Inspector,
+    SmoothMovement,
+    Undo,
+    Quit,
+    Find,
+    PuzzleEditor,
+    RemovePuzzle,
+    AddItem,
+    RemoveItem,
+    PlayerUp,
+    PlayerLeft,
+    PlayerDown,
+    PlayerRight,
+    Levels,
+    /// Plays the next level, or replaces all found cells while finding.
+    Confirm,
+    Blueprints,
+    SaveBlueprint,
+    Symmetry,
+    SymmetryCenter,
+    FitGrid,
+    CenterSelection,
+    SaveBookmark(usize),
+    JumpToBookmark(usize),
+    /// Closes the open menu, or toggles the help.
+    Close,
+}
+
+/// Actions without a bookmark number, in the order they are saved.
+const ACTIONS: [Action; 47] = [
+    Action::MoveUp, Action::MoveLeft, Action::MoveDown, Action::MoveRight,
+    Action::ZoomIn, Action::ZoomOut, Action::GrowTool, Action::ShrinkTool,
+    Action::RotateLeft, Action::RotateRight,
+    Action::Start, Action::Step, Action::SubtickMode, Action::Reset,
+    Action::Import, Action::ExportQ1, Action::ExportQ2, Action::ExportImage, Action::Record,
+    Action::Threaded, Action::CheckLoop, Action::UpdateOrder,
+    Action::Stats, Action::Profile, Action::Heatmap, Action::Inspector, Action::SmoothMovement,
+    Action::Undo, Action::Quit, Action::Find,
+    Action::PuzzleEditor, Action::RemovePuzzle, Action::AddItem, Action::RemoveItem,
+    Action::PlayerUp, Action::PlayerLeft, Action::PlayerDown, Action::PlayerRight,
+    Action::Levels, Action::Confirm, Action::Blueprints, Action::SaveBlueprint,
+    Action::Symmetry, Action::SymmetryCenter, Action::FitGrid, Action::CenterSelection,
+    Action::Close,
+];
+
+impl Action {
+    /// All actions, including one per bookmark.
+    pub fn all() -> impl Iterator<Item = Action> {
+        ACTIONS.into_iter()
+            .chain((0..BOOKMARK_COUNT).map(Action::SaveBookmark))
+            .chain((0..BOOKMARK_COUNT).map(Action::JumpToBookmark))
+    }
+
+    /// Name of the action in the settings file.
+    pub fn name(self) -> String {
+        let name = match self {
+            Action::MoveUp => "move_up",
+            Action::MoveLeft => "move_left",
+            Action::MoveDown => "move_down",
+            Action::MoveRight => "move_right",
+            Action::ZoomIn => "zoom_in",
+            Action::ZoomOut => "zoom_out",
+            Action::GrowTool => "grow_tool",
+            Action::ShrinkTool => "shrink_tool",
+            Action::RotateLeft => "rotate_left",
+            Action::RotateRight => "rotate_right",
+            Action::Start => "start",
+            Action::Step => "step",
+            Action::SubtickMode => "subtick_mode",
+            Action::Reset => "reset",
+            Action::Import => "import",
+            Action::ExportQ1 => "export_q1",
+            Action::ExportQ2 => "export_q2",
+            Action::ExportImage => "export_image",
+            Action::Record => "record",
+            Action::Threaded => "threaded",
+            Action::CheckLoop => "check_loop",
+            Action::UpdateOrder => "update_order",
+            Action::Stats => "stats",
+            Action::Profile => "profile",
+            Action::Heatmap => "heatmap",
+            Action::Inspector => "inspector",
+            Action::SmoothMovement => "smooth_movement",
+            Action::Undo => "undo",
+            Action::Quit => "quit",
+            Action::Find => "find",
+            Action::PuzzleEditor => "puzzle_editor",
+            Action::RemovePuzzle => "remove_puzzle",
+            Action::AddItem => "add_item",
+            Action::RemoveItem => "remove_item",
+            Action::PlayerUp => "player_up",
+            Action::PlayerLeft => "player_left",
+            Action::PlayerDown => "player_down",
+            Action::PlayerRight => "player_right",
+            Action::Levels => "levels",
+            Action::Confirm => "confirm",
+            Action::Blueprints => "blueprints",
+            Action::SaveBlueprint => "save_blueprint",
+            Action::Symmetry => "symmetry",
+            Action::SymmetryCenter => "symmetry_center",
+            Action::FitGrid => "fit_grid",
+            Action::CenterSelection => "center_selection",
+            Action::SaveBookmark(i) => return format!("save_bookmark_{}", i + 1),
+            Action::JumpToBookmark(i) => return format!("jump_to_bookmark_{}", i + 1),
+            Action::Close => "close",
+        };
+        name.to_string()
+    }
+
+    fn default_bindings(self) -> Vec<Binding> {
+        use VirtualKeyCode::*;
+        let key = |key| vec![Binding { key, modifiers: Modifiers::NONE }];
+        let shift = |key| vec![Binding { key, modifiers: Modifiers { shift: true, ..Modifiers::NONE } }];
+        let alt = |key| vec![Binding { key, modifiers: Modifiers { alt: true, ..Modifiers::NONE } }];
+        let command = |key| vec![Binding { key, modifiers: COMMAND }];
+        match self {
+            Action::MoveUp => key(W),
+            Action::MoveLeft => key(A),
+            Action::MoveDown => key(S),
+            Action::MoveRight => key(D),
+            Action::ZoomIn => key(F),
+            Action::ZoomOut => key(R),
+            Action::GrowTool => alt(F),
+            Action::ShrinkTool => alt(R),
+            Action::RotateLeft => key(Q),
+            Action::RotateRight => key(E),
+            Action::Start => key(Space),
+            Action::Step => key(G),
+            Action::SubtickMode => key(H),
+            Action::Reset => key(T),
+            Action::Import => key(I),
+            Action::ExportQ1 => key(O),
+            Action::ExportQ2 => key(P),
+            Action::ExportImage => shift(O),
+            Action::Record => shift(R),
+            Action::Threaded => key(M),
+            Action::CheckLoop => key(N),
+            Action::UpdateOrder => key(U),
+            Action::Stats => key(L),
+            Action::Profile => key(J),
+            Action::Heatmap => shift(L),
+            Action::Inspector => key(X),
+            Action::SmoothMovement => key(Z),
+            Action::Undo => command(Z),
+            Action::Quit => command(Q),
+            Action::Find => command(F),
+            Action::PuzzleEditor => key(K),
+            Action::RemovePuzzle => shift(K),
+            Action::AddItem => key(Equals),
+            Action::RemoveItem => key(Minus),
+            Action::PlayerUp => key(Up),
+            Action::PlayerLeft => key(Left),
+            Action::PlayerDown => key(Down),
+            Action::PlayerRight => key(Right),
+            Action::Levels => key(B),
+            Action::Confirm => key(Return),
+            Action::Blueprints => key(V),
+            Action::SaveBlueprint => key(C),
+            Action::Symmetry => key(Y),
+            Action::SymmetryCenter => shift(Y),
+            Action::FitGrid => key(Home),
+            Action::CenterSelection => shift(Home),
+            Action::SaveBookmark(i) => command(NUMBER_KEYS[i]),
+            Action::JumpToBookmark(i) => key(NUMBER_KEYS[i]),
+            Action::Close => key(Escape),
+        }
+    }
+}
+
+/// Keys of the bookmarks.
+const NUMBER_KEYS: [VirtualKeyCode; BOOKMARK_COUNT] = [
+    VirtualKeyCode::Key1, VirtualKeyCode::Key2, VirtualKeyCode::Key3,
+    VirtualKeyCode::Key4, VirtualKeyCode::Key5, VirtualKeyCode::Key6,
+    VirtualKeyCode::Key7, VirtualKeyCode::Key8, VirtualKeyCode::Key9,
+];
+
+/// Keys that can be bound, named like their `VirtualKeyCode` without the `Key` of number keys.
+const BINDABLE_KEYS: [VirtualKeyCode; 60] = {
+    use VirtualKeyCode::*;
+    [
+        A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+        Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9, Key0,
+        F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+        Up, Left, Down, Right, Space, Return, Escape, Tab, Home, End, Equals, Minus,
+    ]
+};
+
+/// Shortcut modifier, Cmd on macOS and Ctrl everywhere else.
+#[cfg(target_os = "macos")]
+const COMMAND: Modifiers = Modifiers { cmd: true, ..Modifiers::NONE };
+#[cfg(not(target_os = "macos"))]
+const COMMAND: Modifiers = Modifiers { ctrl: true, ..Modifiers::NONE };
+
+/// Modifier keys held together with a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    /// The Cmd key on macOS, the Windows key everywhere else.
+    pub cmd: bool,
+}
+
+impl Modifiers {
+    pub const NONE: Modifiers = Modifiers { ctrl: false, alt: false, shift: false, cmd: false };
+
+    /// The modifiers currently held down, from either side of the keyboard.
+    pub fn held(keys: &HashSet<VirtualKeyCode>) -> Self {
+        let any = |a, b| keys.contains(&a) || keys.contains(&b);
+        Modifiers {
+            ctrl: any(VirtualKeyCode::LControl, VirtualKeyCode::RControl),
+            alt: any(VirtualKeyCode::LAlt, VirtualKeyCode::RAlt),
+            shift: any(VirtualKeyCode::LShift, VirtualKeyCode::RShift),
+            cmd: any(VirtualKeyCode::LWin, VirtualKeyCode::RWin),
+        }
+    }
+
+    /// Whether all of the other modifiers are held too.
+    fn contains(self, other: Modifiers) -> bool {
+        (self.ctrl || !other.ctrl) && (self.alt || !other.alt) && (self.shift || !other.shift) && (self.cmd || !other.cmd)
+    }
+
+    fn count(self) -> usize {
+        [self.ctrl, self.alt, self.shift, self.cmd].iter().filter(|&&m| m).count()
+    }
+}
+
+/// A key together with the modifiers that have to be held.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Binding {
+    pub key: VirtualKeyCode,
+    pub modifiers: Modifiers,
+}
+
+impl Binding {
+    /// Parses a binding like `Ctrl+Shift+Z`.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut parts = text.split('+').map(str::trim).collect::<Vec<_>>();
+        let key = parts.pop().unwrap();
+        let key = BINDABLE_KEYS.into_iter()
+            .find(|&k| key_name(k).eq_ignore_ascii_case(key))
+            .ok_or_else(|| format!("unknown key: {key}"))?;
+
+        let mut modifiers = Modifiers::NONE;
+        for part in parts {
+            match part.to_ascii_lowercase().as_str() {
+                "ctrl" => modifiers.ctrl = true,
+                "alt" => modifiers.alt = true,
+                "shift" => modifiers.shift = true,
+                "cmd" => modifiers.cmd = true,
+                _ => return Err(format!("unknown modifier: {part}")),
+            }
+        }
+        Ok(Binding { key, modifiers })
+    }
+
+    /// The binding as it's written in the settings file and shown in the help.
+    pub fn name(&self) -> String {
+        let Modifiers { ctrl, alt, shift, cmd } = self.modifiers;
+        let mut name = String::new();
+        for (held, modifier) in [(ctrl, "Ctrl+"), (alt, "Alt+"), (shift, "Shift+"), (cmd, "Cmd+")] {
+            if held { name += modifier; }
+        }
+        name.push_str(&key_name(self.key));
+        name
+    }
+}
+
+fn key_name(key: VirtualKeyCode) -> String {
+    let name = format!("{key:?}");
+    name.strip_prefix("Key").map(str::to_string).unwrap_or(name)
+}
+
+/// The keys bound to every action.
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: Vec<(Action, Vec<Binding>)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap { bindings: Action::all().map(|action| (action, action.default_bindings())).collect() }
+    }
+}
+
+impl Keymap {
+    /// The action of a pressed key with the held keys.
+    /// If several bindings match, the one with the most modifiers is used, so `Shift+L` is preferred over `L`.
+    pub fn action(&self, key: VirtualKeyCode, keys: &HashSet<VirtualKeyCode>) -> Option<Action> {
+        let held = Modifiers::held(keys);
+        self.bindings.iter()
+            .flat_map(|(action, bindings)| bindings.iter().map(move |binding| (*action, binding)))
+            .filter(|(_, binding)| binding.key == key && held.contains(binding.modifiers))
+            .max_by_key(|(_, binding)| binding.modifiers.count())
+            .map(|(action, _)| action)
+    }
+
+    /// Whether a key of the action is held down, used for actions that last as long as the key is held.
+    pub fn is_held(&self, action: Action, keys: &HashSet<VirtualKeyCode>) -> bool {
+        let held = Modifiers::held(keys);
+        self.bindings(action).iter().any(|binding| keys.contains(&binding.key) && held.contains(binding.modifiers))
+    }
+
+    pub fn bindings(&self, action: Action) -> &[Binding] {
+        self.bindings.iter().find(|(a, _)| *a == action).map(|(_, bindings)| &bindings[..]).unwrap_or(&[])
+    }
+
+    /// Names of the keys of the action, separated by a comma.
+    pub fn describe(&self, action: Action) -> String {
+        self.bindings(action).iter().map(Binding::name).collect::<Vec<_>>().join(", ")
+    }
+
+    fn set(&mut self, action: Action, bindings: Vec<Binding>) {
+        if let Some(entry) = self.bindings.iter_mut().find(|(a, _)| *a == action) {
+            entry.1 = bindings;
+        }
+    }
+}
+
+/// Settings and key bindings, saved in `settings.txt` in the user's config directory.
+///
+/// Every line is a `name = value` pair, key bindings are named `key.<action>` and
+/// separated by commas. Lines starting with `#` are ignored.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    /// Size of the grid at startup.
+    pub grid_width: usize,
+    pub grid_height: usize,
+    pub threaded: bool,
+    pub check_loop: bool,
+    /// Factor the zoom changes by for every key press or scroll step.
+    pub zoom_speed: f32,
+    pub keymap: Keymap,
+    /// The settings file, not set if there is no config directory.
+    pub path: Option<PathBuf>,
+}
+
+impl Settings {
+    /// Loads the settings, using the defaults for missing or invalid values.
+    /// The file is created with the defaults if it doesn't exist yet.
+    pub fn load() -> Self {
+        let path = dirs::config_dir().map(|dir| dir.join("quell-machine").join("settings.txt"));
+        let mut settings = Settings {
+            grid_width: DEFAULT_GRID_WIDTH,
+            grid_height: DEFAULT_GRID_HEIGHT,
+            threaded: false,
+            check_loop: false,
+            zoom_speed: 1.2,
+            keymap: Keymap::default(),
+            path,
+        };
+        let Some(path) = settings.path.clone() else { return settings };
+
+        match fs::read_to_string(&path) {
+            Ok(text) => {
+                for (i, line) in text.lines().enumerate() {
+                    if let Err(err) = settings.parse_line(line) {
+                        eprintln!("{}:{}: {err}", path.display(), i + 1);
+                    }
+                }
+            },
+            Err(_) => settings.save(),
+        }
+        settings
+    }
+
+    fn parse_line(&mut self, line: &str) -> Result<(), String> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') { return Ok(()); }
+        let (name, value) = line.split_once('=').ok_or("expected `name = value`")?;
+        let (name, value) = (name.trim(), value.trim());
+
+        let invalid = || format!("invalid value for {name}: {value}");
+        let size = || match value.parse::<usize>() {
+            Ok(size) if size > MAX_GRID_SIZE => Err(format!("{name} can be at most {MAX_GRID_SIZE}, using the default")),
+            Ok(size) if size > 0 => Ok(size),
+            _ => Err(invalid()),
+        };
+        match name {
+            "grid_width" => self.grid_width = size()?,
+            "grid_height" => self.grid_height = size()?,
+            "threaded" => self.threaded = value.parse().map_err(|_| invalid())?,
+            "check_loop" => self.check_loop = value.parse().map_err(|_| invalid())?,
+            "zoom_speed" => {
+                self.zoom_speed = value.parse::<f32>().ok().filter(|speed| speed.is_finite() && *speed > 1.0).ok_or_else(invalid)?;
+            },
+            _ => {
+                let action = name.strip_prefix("key.")
+                    .and_then(|name| Action::all().find(|action| action.name() == name))
+                    .ok_or_else(|| format!("unknown setting: {name}"))?;
+                let bindings = value.split(',')
+                    .filter(|binding| !binding.trim().is_empty())
+                    .map(Binding::parse)
+                    .collect::<Result<Vec<_>, _>>()?;
+                self.keymap.set(action, bindings);
+            },
+        }
+        Ok(())
+    }
+
+    /// Writes all settings to the settings file.
+    pub fn save(&self) {
+        let Some(path) = &self.path else { return };
+
+        let mut text = format!(
+            "grid_width = {}\ngrid_height = {}\nthreaded = {}\ncheck_loop = {}\nzoom_speed = {}\n\n# keys are separated by commas, leave them empty to unbind an action\n",
+            self.grid_width, self.grid_height, self.threaded, self.check_loop, self.zoom_speed,
+        );
+        for action in Action::all() {
+            text += &format!("key.{} = {}\n", action.name(), self.keymap.describe(action));
+        }
+
+        let result = path.parent().map_or(Ok(()), fs::create_dir_all).and_then(|_| fs::write(path, text));
+        if let Err(err) = result {
+            eprintln!("couldn't save settings: {err}");
+        }
+    }
+}
+
+/// Folder for the files the game writes besides the settings, like the level progress.
+/// Not set if the platform has no data directory.
+pub fn data_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("quell-machine"))
+}
+
+/// Folder for exported images and recordings, inside the pictures folder if the platform has one.
+pub fn export_dir() -> Option<PathBuf> {
+    dirs::picture_dir().map(|dir| dir.join("Quell Machine"))
+        .or_else(|| data_dir().map(|dir| dir.join("exports")))
+}
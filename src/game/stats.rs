@@ -0,0 +1,94 @@
+use std::collections::VecDeque;
+
+use super::{cells::Grid, events::UpdateEvent};
+
+/// How many ticks of population history are kept.
+pub const HISTORY_LENGTH: usize = 200;
+
+/// Statistics about a running simulation.
+/// Collected from the cells on a grid and the events recorded during updates.
+#[derive(Debug, Clone)]
+pub struct Statistics {
+    /// Amount of cells on the grid, indexed by cell type.
+    pub counts: [usize; 256],
+    /// Total amount of cells on the grid.
+    pub population: usize,
+    /// Cells created per tick since the last collection.
+    pub created_per_tick: f32,
+    /// Cells destroyed per tick since the last collection.
+    pub destroyed_per_tick: f32,
+    pub total_created: usize,
+    pub total_destroyed: usize,
+    pub enemies_killed: usize,
+    pub rotations: usize,
+    pub failed_pushes: usize,
+    /// Population after each tick, oldest first.
+    pub history: VecDeque<usize>,
+    last_tick: u32,
+}
+
+impl Statistics {
+    pub fn new() -> Self {
+        Statistics {
+            counts: [0; 256],
+            population: 0,
+            created_per_tick: 0.0,
+            destroyed_per_tick: 0.0,
+            total_created: 0,
+            total_destroyed: 0,
+            enemies_killed: 0,
+            rotations: 0,
+            failed_pushes: 0,
+            history: VecDeque::with_capacity(HISTORY_LENGTH),
+            last_tick: 0,
+        }
+    }
+
+    /// Recounts the cells on the grid and consumes all events recorded on it.
+    /// Enables event recording on the grid if it isn't already.
+    pub fn collect(&mut self, grid: &mut Grid) {
+        if grid.tick_count < self.last_tick {
+            // the grid was reset
+            *self = Statistics::new();
+        }
+
+        self.counts = [0; 256];
+        self.population = 0;
+        grid.for_each(|_, _, cell| {
+            if let Some(cell) = cell {
+                self.counts[cell.id() as usize] += 1;
+                self.population += 1;
+            }
+        });
+
+        let mut created = 0;
+        let mut destroyed = 0;
+        for event in grid.events.get_or_insert_with(Vec::new).drain(..) {
+            match event {
+                UpdateEvent::Generated { .. } => created += 1,
+                UpdateEvent::Trashed { .. } => destroyed += 1,
+                UpdateEvent::EnemyKilled { .. } => {
+                    // the enemy and the cell moved into it
+                    destroyed += 2;
+                    self.enemies_killed += 1;
+                },
+                UpdateEvent::Rotated { .. } => self.rotations += 1,
+                UpdateEvent::PushFailed { .. } => self.failed_pushes += 1,
+            }
+        }
+        self.total_created += created;
+        self.total_destroyed += destroyed;
+
+        let ticks = grid.tick_count - self.last_tick;
+        if ticks > 0 {
+            self.created_per_tick = created as f32 / ticks as f32;
+            self.destroyed_per_tick = destroyed as f32 / ticks as f32;
+            self.last_tick = grid.tick_count;
+
+            self.history.push_back(self.population);
+            if self.history.len() > HISTORY_LENGTH {
+                self.history.pop_front();
+            }
+        }
+    }
+}
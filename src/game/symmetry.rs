@@ -0,0 +1,115 @@
+use super::{cells::{Cell, CellType, Grid}, cell_data::{cell_data, ROTATOR_CW, ROTATOR_CCW, GENERATOR_CW, GENERATOR_CCW}, direction::Direction};
+
+/// How placed cells are copied around the symmetry center.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Symmetry {
+    None,
+    /// Mirrored from left to right.
+    Horizontal,
+    /// Mirrored from top to bottom.
+    Vertical,
+    /// Mirrored from left to right and from top to bottom.
+    FourWay,
+    /// Rotated by quarter turns.
+    Rotational,
+}
+
+impl Symmetry {
+    pub fn next(self) -> Symmetry {
+        match self {
+            Symmetry::None => Symmetry::Horizontal,
+            Symmetry::Horizontal => Symmetry::Vertical,
+            Symmetry::Vertical => Symmetry::FourWay,
+            Symmetry::FourWay => Symmetry::Rotational,
+            Symmetry::Rotational => Symmetry::None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Symmetry::None => "None",
+            Symmetry::Horizontal => "Horizontal",
+            Symmetry::Vertical => "Vertical",
+            Symmetry::FourWay => "4-way",
+            Symmetry::Rotational => "Rotational",
+        }
+    }
+
+    /// All placements the symmetry creates from a placement, starting with the placement itself.
+    /// Positions are only returned once, even if they lie on an axis.
+    ///
+    /// The center is given in half cells, so `(1, 1)` is the center of the cell at `(0, 0)`.
+    pub fn images(self, center: (isize, isize), x: isize, y: isize, cell: Option<&Cell>) -> Vec<(isize, isize, Option<Cell>)> {
+        let (cx, cy) = center;
+        let mirror_x = |x: isize| cx - x - 1;
+        let mirror_y = |y: isize| cy - y - 1;
+        // a quarter turn clockwise, the y axis points up
+        let rotate = |(x, y): (isize, isize)| ((cx - cy) / 2 + y, (cx + cy) / 2 - x - 1);
+
+        let flip_x = |d: Direction| match d { Direction::Right | Direction::Left => d.flip(), _ => d };
+        let flip_y = |d: Direction| match d { Direction::Up | Direction::Down => d.flip(), _ => d };
+
+        let mut images = vec![(x, y, cell.cloned())];
+        match self {
+            Symmetry::None => {},
+            Symmetry::Horizontal => {
+                images.push((mirror_x(x), y, transform(cell, true, flip_x)));
+            },
+            Symmetry::Vertical => {
+                images.push((x, mirror_y(y), transform(cell, true, flip_y)));
+            },
+            Symmetry::FourWay => {
+                images.push((mirror_x(x), y, transform(cell, true, flip_x)));
+                images.push((x, mirror_y(y), transform(cell, true, flip_y)));
+                // mirrored twice, which is a half turn
+                images.push((mirror_x(x), mirror_y(y), transform(cell, false, Direction::flip)));
+            },
+            Symmetry::Rotational => {
+                let mut pos = (x, y);
+                for turns in 1..4 {
+                    pos = rotate(pos);
+                    images.push((pos.0, pos.1, transform(cell, false, |d| d + turns)));
+                }
+            },
+        }
+
+        let mut seen = Vec::with_capacity(images.len());
+        images.retain(|&(x, y, _)| {
+            let new = !seen.contains(&(x, y));
+            seen.push((x, y));
+            new
+        });
+        images
+    }
+}
+
+/// Changes the direction of a cell, keeping the direction within the sides of the cell type.
+/// Mirrored cells that turn one way are replaced with their counterpart turning the other way.
+fn transform(cell: Option<&Cell>, mirrored: bool, f: impl Fn(Direction) -> Direction) -> Option<Cell> {
+    let cell = cell?;
+    let id = if mirrored { mirrored_id(cell.id()) } else { cell.id() };
+    let mut direction = cell.direction();
+    let sides = cell_data(id).sides as u8;
+    if sides > 1 {
+        direction = f(direction) % sides;
+    }
+    Some(Cell::new(id, direction))
+}
+
+/// The cell type that does the same as a cell type when mirrored.
+fn mirrored_id(id: CellType) -> CellType {
+    match id {
+        ROTATOR_CW => ROTATOR_CCW,
+        ROTATOR_CCW => ROTATOR_CW,
+        GENERATOR_CW => GENERATOR_CCW,
+        GENERATOR_CCW => GENERATOR_CW,
+        _ => id,
+    }
+}
+
+/// Center of the grid in half cells.
+/// The center is moved by half a cell if needed, so quarter turns keep cells on the grid positions.
+pub fn grid_center(grid: &Grid) -> (isize, isize) {
+    let (cx, cy) = (grid.width as isize, grid.height as isize);
+    if (cx - cy) % 2 == 0 { (cx, cy) } else { (cx, cy - 1) }
+}
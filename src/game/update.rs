@@ -1,6 +1,6 @@
-use std::{sync::{Arc, Mutex}, thread, time::Instant};
+use std::{sync::{Arc, Mutex}, thread, time::Instant, mem, ptr};
 
-use super::{cells::Grid, manipulation::{push, rotate_by, rotate_to, pull, MoveForce, can_move, is_trash, can_generate}, direction::Direction, cell_data::{MOVER, GENERATOR, ROTATOR_CCW, ROTATOR_CW, ORIENTATOR, PULLER, PULLSHER, MIRROR, CROSSMIRROR, TRASHMOVER, SPEED, GENERATOR_CW, GENERATOR_CCW, TRASHPULLER, STONE, REPLICATOR, SUCKER, GENERATOR_CROSS, PHYSICAL_GENERATOR, ROTATOR_180, TUNNEL, FIXED_PULLSHER}};
+use super::{cells::{Cell, CellType, Grid}, manipulation::{push, rotate_by, rotate_to, pull, MoveForce, PushResult, can_move, is_trash, can_generate}, direction::Direction, events::{UpdateEvent, CellMotion}, recording::frame, cell_data::{cell_data, MOVER, GENERATOR, ROTATOR_CCW, ROTATOR_CW, ORIENTATOR, PULLER, PULLSHER, MIRROR, CROSSMIRROR, TRASHMOVER, SPEED, GENERATOR_CW, GENERATOR_CCW, TRASHPULLER, STONE, REPLICATOR, SUCKER, GENERATOR_CROSS, PHYSICAL_GENERATOR, ROTATOR_180, TUNNEL, FIXED_PULLSHER, REDIRECTOR, PLAYER}};
 
 macro_rules! loop_each {
     (for $x:ident, $y:ident, $name:ident in $grid:expr; $code:block) => {
@@ -62,14 +62,37 @@ pub fn run_update_loop(grid: Grid) -> UpdateState {
             let start = Instant::now();
             update(&mut grid);
             let elapsed = start.elapsed();
+            if let Some(mut frames) = grid.frames.take() {
+                frames.push(frame(&grid));
+                grid.frames = Some(frames);
+            }
 
             let stop;
-            let grid = grid.clone();
+            let events = grid.events.as_mut().map(mem::take);
+            let frames = grid.frames.as_mut().map(mem::take);
+            let mut shared = grid.clone();
+            shared.events = events;
+            shared.frames = frames;
+            shared.input.clear();
             let elapsed = elapsed.as_secs_f32() * 1000.0;
             {
                 let mut s = s.lock().unwrap();
                 stop = !s.0;
-                s.1 = grid;
+                // recordings enabled or disabled by the main thread since the last tick
+                grid.copy_recording(&s.1);
+                shared.copy_recording(&s.1);
+                // keep the events the main thread didn't collect yet
+                if let (Some(uncollected), Some(events)) = (s.1.events.take(), &mut shared.events) {
+                    events.splice(0..0, uncollected);
+                }
+                if let (Some(uncollected), Some(frames)) = (s.1.frames.take(), &mut shared.frames) {
+                    frames.splice(0..0, uncollected);
+                }
+                // inputs queued by the main thread since the last tick
+                for dir in s.1.input.drain(..) {
+                    grid.queue_input(dir);
+                }
+                s.1 = shared;
                 s.2 = elapsed;
             }
             if stop { break; }
@@ -79,50 +102,281 @@ pub fn run_update_loop(grid: Grid) -> UpdateState {
     state
 }
 
+/// A single pass over the grid updating a group of cells.
+///
+/// Cell types link to the subtick updating them with `CellBehavior::subtick`.
+/// The subtick is skipped if none of them are on the grid.
+pub struct Subtick {
+    pub name: &'static str,
+    pub run: &'static (dyn Fn(&mut Grid) + Sync),
+}
+
+// helper for easier subtick definitions
+macro_rules! subticks {
+    ($( $name:ident: $fn_name:ident )*) => {
+        $(
+            pub static $name: Subtick = Subtick {
+                name: stringify!($fn_name),
+                run: &$fn_name,
+            };
+        )*
+    }
+}
+
+subticks! {
+    MIRRORS:         do_mirrors
+    CROSSMIRRORS:    do_crossmirrors
+    TUNNELS:         do_tunnels
+    FIXED_PULLSHERS: do_fixed_pullsher
+    SUCKERS:         do_suckers
+    GENS:            do_gens
+    ANGLED_GENS:     do_angled_gens
+    PHYSICAL_GENS:   do_physical_gens
+    CROSS_GENS:      do_cross_gens
+    REPLICATORS:     do_replicators
+    // POSTOFFICES:     do_postoffices
+    ROTATORS:        do_rotators
+    ORIENTATORS:     do_orientators
+    STONES:          do_stones
+    // MAILBOXES:       do_mailboxes
+    PULLSHERS:       do_pullshers
+    TRASHPULLERS:    do_trashpullers
+    PULLERS:         do_pullers
+    TRASHMOVERS:     do_trashmovers
+    MOVERS:          do_movers
+    SPEEDS:          do_speeds
+    PLAYERS:         do_players
+}
+
+// update order presets
+static QUELL_ORDER: &[&Subtick] = &[
+    &MIRRORS, &CROSSMIRRORS, &TUNNELS, &FIXED_PULLSHERS, &SUCKERS,
+    &GENS, &ANGLED_GENS, &PHYSICAL_GENS, &CROSS_GENS, &REPLICATORS,
+    &ROTATORS, &ORIENTATORS,
+    &STONES,
+    &PULLSHERS, &TRASHPULLERS, &PULLERS, &TRASHMOVERS, &MOVERS, &SPEEDS,
+    &PLAYERS,
+];
+static CELL_MACHINE_ORDER: &[&Subtick] = &[
+    &GENS, &ANGLED_GENS, &PHYSICAL_GENS, &CROSS_GENS, &REPLICATORS,
+    &ROTATORS, &ORIENTATORS,
+    &MIRRORS, &CROSSMIRRORS, &TUNNELS, &FIXED_PULLSHERS, &SUCKERS,
+    &STONES,
+    &PULLSHERS, &TRASHPULLERS, &PULLERS, &TRASHMOVERS, &MOVERS, &SPEEDS,
+    &PLAYERS,
+];
+static ROTATORS_FIRST_ORDER: &[&Subtick] = &[
+    &ROTATORS, &ORIENTATORS,
+    &MIRRORS, &CROSSMIRRORS, &TUNNELS, &FIXED_PULLSHERS, &SUCKERS,
+    &GENS, &ANGLED_GENS, &PHYSICAL_GENS, &CROSS_GENS, &REPLICATORS,
+    &STONES,
+    &PULLSHERS, &TRASHPULLERS, &PULLERS, &TRASHMOVERS, &MOVERS, &SPEEDS,
+    &PLAYERS,
+];
+
+/// The order subticks are executed in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum UpdateOrder {
+    /// The default order of this game.
+    Quell,
+    /// Order of the original Cell Machine: generators, then rotators, then movers.
+    /// Cells it didn't have are updated next to their closest relative.
+    CellMachine,
+    /// Rotators and orientators are updated before generators, like in many Cell Machine mods.
+    RotatorsFirst,
+}
+
+impl UpdateOrder {
+    pub const ALL: [UpdateOrder; 3] = [UpdateOrder::Quell, UpdateOrder::CellMachine, UpdateOrder::RotatorsFirst];
+
+    /// The subticks in the order they are executed.
+    pub fn subticks(self) -> &'static [&'static Subtick] {
+        match self {
+            UpdateOrder::Quell => QUELL_ORDER,
+            UpdateOrder::CellMachine => CELL_MACHINE_ORDER,
+            UpdateOrder::RotatorsFirst => ROTATORS_FIRST_ORDER,
+        }
+    }
+
+    /// Human readable name of the order.
+    pub fn name(self) -> &'static str {
+        match self {
+            UpdateOrder::Quell => "Quell",
+            UpdateOrder::CellMachine => "Cell Machine",
+            UpdateOrder::RotatorsFirst => "Rotators first",
+        }
+    }
+
+    /// Short identifier used in level codes.
+    pub fn code(self) -> &'static str {
+        match self {
+            UpdateOrder::Quell => "q",
+            UpdateOrder::CellMachine => "cm",
+            UpdateOrder::RotatorsFirst => "rf",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<UpdateOrder> {
+        UpdateOrder::ALL.into_iter().find(|order| order.code() == code)
+    }
+
+    /// The next order, used for cycling through all of them.
+    pub fn next(self) -> UpdateOrder {
+        let index = UpdateOrder::ALL.iter().position(|&order| order == self).unwrap();
+        UpdateOrder::ALL[(index + 1) % UpdateOrder::ALL.len()]
+    }
+}
+
+/// Timing of a single subtick during an update step.
+#[derive(Debug, Clone)]
+pub struct SubtickProfile {
+    pub name: &'static str,
+    /// Time spent in the subtick in milliseconds.
+    pub time: f32,
+    /// Amount of cells on the grid the subtick is responsible for.
+    pub cells: usize,
+}
+
 /// Performs a single update step.
 pub fn update(grid: &mut Grid) {
-    let mut cell_flags = 0u64;
+    let mut profile = grid.profile.take();
+    let active = active_subticks(grid, profile.is_some());
+
+    if let Some(profile) = &mut profile {
+        profile.clear();
+    }
+
+    for &(subtick, cells) in &active {
+        if let Some(profile) = &mut profile {
+            let start = Instant::now();
+            (subtick.run)(grid);
+            profile.push(SubtickProfile {
+                name: subtick.name,
+                time: start.elapsed().as_secs_f32() * 1000.0,
+                cells,
+            });
+        }
+        else {
+            (subtick.run)(grid);
+        }
+    }
+
+    grid.profile = profile;
+    grid.end_activity();
+    grid.tick_count += 1;
+}
+
+/// Prepares the grid for a tick and returns the subticks of the cells on the grid in update order,
+/// with the amount of cells using them if `count_cells` is set.
+fn active_subticks(grid: &mut Grid, count_cells: bool) -> Vec<(&'static Subtick, usize)> {
+    let mut cell_flags = [0u64; 4];
+    let mut cell_counts = [0usize; 256];
 
     for y in 0..grid.height as isize {
         for x in 0..grid.width as isize {
             if let Some(cell) = grid.get_mut(x, y) {
                 cell.set_updated(false);
-                cell_flags |= 1 << cell.id();
+                cell_flags[cell.id() as usize >> 6] |= 1 << (cell.id() & 63);
+                if count_cells {
+                    cell_counts[cell.id() as usize] += 1;
+                }
+            }
+        }
+    }
+
+    // subticks of the cells on the grid
+    let mut active: Vec<(&'static Subtick, usize)> = Vec::new();
+    for id in 1..=CellType::MAX {
+        if cell_flags[id as usize >> 6] & (1 << (id & 63)) == 0 { continue; }
+        if let Some(subtick) = cell_data(id).behavior.subtick() {
+            match active.iter_mut().find(|(s, _)| ptr::eq(*s, subtick)) {
+                Some((_, count)) => *count += cell_counts[id as usize],
+                None => active.push((subtick, cell_counts[id as usize])),
             }
         }
     }
 
-    macro_rules! subticks {
-        ($( $($cell:ident),*: $fn_name:ident)* ) => {
-            $( if cell_flags & ($(1 << $cell)|*) != 0 { $fn_name(grid); } )*
+    grid.begin_activity();
+    if let Some(motion) = &mut grid.motion {
+        motion.clear();
+        motion.resize(grid.width * grid.height, CellMotion::default());
+    }
+
+    grid.has_redirectors = cell_flags[REDIRECTOR as usize >> 6] & (1 << (REDIRECTOR & 63)) != 0;
+
+    // subticks missing from the update order (e.g. of registered cells) run last
+    let order = grid.update_order.subticks();
+    active.sort_by_key(|(subtick, _)| order.iter().position(|s| ptr::eq(*s, *subtick)).unwrap_or(order.len()));
+    active
+}
+
+/// Runs a tick one subtick at a time, for debugging interactions between subticks.
+pub struct TickStepper {
+    subticks: Vec<&'static Subtick>,
+    next: usize,
+}
+
+impl TickStepper {
+    /// Prepares a tick like `update` does. A tick without subticks is finished right away.
+    pub fn new(grid: &mut Grid) -> Self {
+        let subticks: Vec<_> = active_subticks(grid, false).into_iter().map(|(subtick, _)| subtick).collect();
+        if subticks.is_empty() {
+            grid.end_activity();
+            grid.tick_count += 1;
+        }
+        TickStepper { subticks, next: 0 }
+    }
+
+    /// Runs the next subtick and returns it. The tick is finished after the last subtick.
+    pub fn step(&mut self, grid: &mut Grid) -> Option<&'static Subtick> {
+        let subtick = *self.subticks.get(self.next)?;
+        (subtick.run)(grid);
+        self.next += 1;
+        if self.is_finished() {
+            grid.end_activity();
+            grid.tick_count += 1;
         }
+        Some(subtick)
     }
 
-    subticks! {
-        MIRROR          : do_mirrors
-        CROSSMIRROR     : do_crossmirrors
-        TUNNEL          : do_tunnels
-        FIXED_PULLSHER  : do_fixed_pullsher
-        SUCKER          : do_suckers
-        GENERATOR       : do_gens
-        GENERATOR_CW, GENERATOR_CCW: do_angled_gens
-        PHYSICAL_GENERATOR: do_physical_gens
-        GENERATOR_CROSS : do_cross_gens
-        REPLICATOR      : do_replicators
-        // POSTOFFICE      : do_postoffices
-        ROTATOR_CW, ROTATOR_CCW, ROTATOR_180: do_rotators
-        ORIENTATOR      : do_orientators
-        STONE           : do_stones
-        // MAILBOX         : do_mailboxes
-        PULLSHER        : do_pullshers
-        TRASHPULLER     : do_trashpullers
-        PULLER          : do_pullers
-        TRASHMOVER      : do_trashmovers
-        MOVER           : do_movers
-        SPEED           : do_speeds
+    /// Runs the remaining subticks of the tick.
+    pub fn finish(&mut self, grid: &mut Grid) {
+        while self.step(grid).is_some() {}
     }
 
-    grid.tick_count += 1;
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.subticks.len()
+    }
+
+    /// Amount of subticks that ran and amount of subticks in the tick.
+    pub fn progress(&self) -> (usize, usize) {
+        (self.next, self.subticks.len())
+    }
+}
+
+/// Pushes a generated cell into the grid and records it.
+#[inline(always)]
+fn generate(grid: &mut Grid, x: isize, y: isize, dir: Direction, cell: Cell) -> PushResult {
+    let id = cell.id();
+    if id == REDIRECTOR {
+        grid.has_redirectors = true;
+    }
+    let result = push(grid, x, y, dir, 1, Some(cell), false);
+    if result.did_move() {
+        grid.emit(UpdateEvent::Generated { x, y, id });
+    }
+    result
+}
+
+/// Calls `f` once for every cell of the type that wasn't updated yet,
+/// in the same order movers are updated.
+pub fn update_each_dir(grid: &mut Grid, id: CellType, mut f: impl FnMut(&mut Grid, isize, isize, Direction)) {
+    loop_each_dir!(for dir, x, y, cell in grid; {
+        if cell.id() == id && cell.direction() == dir && !cell.updated() {
+            cell.set_updated(true);
+            f(grid, x, y, dir);
+        }
+    });
 }
 
 fn do_mirrors(grid: &mut Grid) {
@@ -137,6 +391,8 @@ fn do_mirrors(grid: &mut Grid) {
             let cell_left = cell_left.take();
             grid.set_cell(x - 1, y, cell_right.take());
             grid.set_cell(x + 1, y, cell_left);
+            grid.swap_motion(x - 1, y, x + 1, y);
+            touch_swap(grid, x - 1, y, x + 1, y);
         }
     });
     loop_each!(for x, y, cell in grid; {
@@ -150,10 +406,20 @@ fn do_mirrors(grid: &mut Grid) {
             let cell_up = cell_up.take();
             grid.set_cell(x, y + 1, cell_down.take());
             grid.set_cell(x, y - 1, cell_up);
+            grid.swap_motion(x, y + 1, x, y - 1);
+            touch_swap(grid, x, y + 1, x, y - 1);
         }
     });
 }
 
+/// Records both positions of a swap as changed, unless both are empty.
+fn touch_swap(grid: &mut Grid, x1: isize, y1: isize, x2: isize, y2: isize) {
+    if grid.get(x1, y1).is_some() || grid.get(x2, y2).is_some() {
+        grid.touch(x1, y1);
+        grid.touch(x2, y2);
+    }
+}
+
 fn do_crossmirrors(grid: &mut Grid) {
     loop_each!(for x, y, cell in grid; {
         if cell.id() == CROSSMIRROR && !cell.updated() {
@@ -170,6 +436,8 @@ fn do_crossmirrors(grid: &mut Grid) {
                 let cell_left = cell_left.take();
                 grid.set_cell(x - 1, y, cell_right.take());
                 grid.set_cell(x + 1, y, cell_left);
+                grid.swap_motion(x - 1, y, x + 1, y);
+                touch_swap(grid, x - 1, y, x + 1, y);
             }
 
             let cell_up = grid.get_mut(x, y + 1);
@@ -181,9 +449,11 @@ fn do_crossmirrors(grid: &mut Grid) {
                 can_move(cell, Direction::Up, MoveForce::Swap)
             } else { true };
             if up_movable && down_movable {
-                let cell_up = cell_left.take();
+                let cell_up = cell_up.take();
                 grid.set_cell(x, y + 1, cell_down.take());
                 grid.set_cell(x, y - 1, cell_up);
+                grid.swap_motion(x, y + 1, x, y - 1);
+                touch_swap(grid, x, y + 1, x, y - 1);
             }
         }
     });
@@ -197,8 +467,13 @@ fn do_tunnels(grid: &mut Grid) {
         if cell.id() == TUNNEL && cell.direction() == dir && !cell.updated() {
             cell.set_updated(true);
             if let Some(cell) = grid.get(x + cell_offset.x, y + cell_offset.y) {
-                if can_move(cell, dir, MoveForce::Push) && push(grid, x + push_offset.x, y + push_offset.y, dir, 1, Some(cell.clone()), true).did_move() {
-                    grid.set_cell(x + cell_offset.x, y + cell_offset.y, None);
+                if can_move(cell, dir, MoveForce::Push) {
+                    let result = push(grid, x + push_offset.x, y + push_offset.y, dir, 1, Some(cell.clone()), true);
+                    if result.did_move() {
+                        grid.set_cell(x + cell_offset.x, y + cell_offset.y, None);
+                        grid.touch(x + cell_offset.x, y + cell_offset.y);
+                        carry_motion(grid, x + cell_offset.x, y + cell_offset.y, x + push_offset.x, y + push_offset.y, result);
+                    }
                 }
             }
         }
@@ -213,15 +488,31 @@ fn do_fixed_pullsher(grid: &mut Grid) {
         if cell.id() == FIXED_PULLSHER && cell.direction() == dir && !cell.updated() {
             cell.set_updated(true);
             if let Some(cell) = grid.get(x + cell_offset.x, y + cell_offset.y) {
-                if can_move(cell, dir, MoveForce::Push) && !is_trash(cell, dir.flip()) && push(grid, x + push_offset.x, y + push_offset.y, dir, 1, Some(cell.clone()), true).did_move() {
-                    grid.set_cell(x + cell_offset.x, y + cell_offset.y, None);
-                    pull(grid, x + cell_offset.x * 2, y + cell_offset.y * 2, dir);
+                if can_move(cell, dir, MoveForce::Push) && !is_trash(cell, dir.flip()) {
+                    let result = push(grid, x + push_offset.x, y + push_offset.y, dir, 1, Some(cell.clone()), true);
+                    if result.did_move() {
+                        grid.set_cell(x + cell_offset.x, y + cell_offset.y, None);
+                        grid.touch(x + cell_offset.x, y + cell_offset.y);
+                        carry_motion(grid, x + cell_offset.x, y + cell_offset.y, x + push_offset.x, y + push_offset.y, result);
+                        pull(grid, x + cell_offset.x * 2, y + cell_offset.y * 2, dir);
+                    }
                 }
             }
         }
     });
 }
 
+/// Records the motion of a cell carried past a tunnel, which `push` only moved by one cell.
+fn carry_motion(grid: &mut Grid, from_x: isize, from_y: isize, to_x: isize, to_y: isize, result: PushResult) {
+    if result.did_move_survive() {
+        grid.move_motion(from_x, from_y, to_x, to_y);
+    }
+    else if let Some(motion) = grid.motion_mut(from_x, from_y) {
+        // the cell was trashed
+        *motion = CellMotion::default();
+    }
+}
+
 fn do_suckers(grid: &mut Grid) {
     loop_each_dir!(for dir {
         let push_offset = dir.to_vector();
@@ -243,7 +534,7 @@ fn do_gens(grid: &mut Grid) {
             cell.set_updated(true);
             if let Some(cell) = grid.get(x + cell_offset.x, y + cell_offset.y) {
                 if can_generate(cell) {
-                    push(grid, x + push_offset.x, y + push_offset.y, dir, 1, Some(cell.clone()), false);
+                    generate(grid, x + push_offset.x, y + push_offset.y, dir, cell.clone());
                 }
             }
         }
@@ -260,7 +551,7 @@ fn do_angled_gens(grid: &mut Grid) {
                 let mut cell = cell.clone();
                 cell.set_direction(cell.direction().rotate_right());
                 let push_offset = dir.rotate_right().to_vector();
-                push(grid, x + push_offset.x, y + push_offset.y, dir.rotate_right(), 1, Some(cell), false);
+                generate(grid, x + push_offset.x, y + push_offset.y, dir.rotate_right(), cell);
             }
         }
         else if cell.id() == GENERATOR_CCW && cell.direction() == dir && !cell.updated() {
@@ -269,7 +560,7 @@ fn do_angled_gens(grid: &mut Grid) {
                 let mut cell = cell.clone();
                 cell.set_direction(cell.direction().rotate_left());
                 let push_offset = dir.rotate_left().to_vector();
-                push(grid, x + push_offset.x, y + push_offset.y, dir.rotate_left(), 1, Some(cell), false);
+                generate(grid, x + push_offset.x, y + push_offset.y, dir.rotate_left(), cell);
             }
         }
     });
@@ -283,8 +574,8 @@ fn do_physical_gens(grid: &mut Grid) {
         if cell.id() == PHYSICAL_GENERATOR && cell.direction() == dir && !cell.updated() {
             cell.set_updated(true);
             if let Some(cell) = grid.get(x + cell_offset.x, y + cell_offset.y) {
-                if can_generate(cell) && !push(grid, x + push_offset.x, y + push_offset.y, dir, 1, Some(cell.clone()), false).did_move() {
-                    push(grid, x, y, dir.flip(), 1, Some(cell.clone()), false);
+                if can_generate(cell) && !generate(grid, x + push_offset.x, y + push_offset.y, dir, cell.clone()).did_move() {
+                    generate(grid, x, y, dir.flip(), cell.clone());
                 }
             }
         }
@@ -302,12 +593,12 @@ fn do_cross_gens(grid: &mut Grid) {
             cell.set_updated(true);
             if let Some(cell) = grid.get(x + cell_offset_1.x, y + cell_offset_1.y) {
                 if can_generate(cell) {
-                    push(grid, x + push_offset_1.x, y + push_offset_1.y, dir, 1, Some(cell.clone()), false);
+                    generate(grid, x + push_offset_1.x, y + push_offset_1.y, dir, cell.clone());
                 }
             }
             if let Some(cell) = grid.get(x + cell_offset_2.x, y + cell_offset_2.y) {
                 if can_generate(cell) {
-                    push(grid, x + push_offset_2.x, y + push_offset_2.y, dir.rotate_left(), 1, Some(cell.clone()), false);
+                    generate(grid, x + push_offset_2.x, y + push_offset_2.y, dir.rotate_left(), cell.clone());
                 }
             }
         }
@@ -322,7 +613,7 @@ fn do_replicators(grid: &mut Grid) {
             cell.set_updated(true);
             if let Some(cell) = grid.get(x + push_offset.x, y + push_offset.y) {
                 if can_generate(cell) {
-                    push(grid, x + push_offset.x, y + push_offset.y, dir, 1, Some(cell.clone()), false);
+                    generate(grid, x + push_offset.x, y + push_offset.y, dir, cell.clone());
                 }
             }
         }
@@ -507,7 +798,9 @@ fn do_trashpullers(grid: &mut Grid) {
             cell.set_updated(true);
             if let Some(pushed) = grid.get(x + off.x, y + off.y) {
                 if can_move(pushed, dir, MoveForce::Pull) && !is_trash(pushed, dir) {
+                    grid.emit(UpdateEvent::Trashed { x: x + off.x, y: y + off.y, id: pushed.id() });
                     grid.delete(x + off.x, y + off.y);
+                    grid.touch(x + off.x, y + off.y);
                     if grid.get(x - off.x, y - off.y).is_none() {
                         pull(grid, x, y, dir);
                     }
@@ -540,6 +833,8 @@ fn do_trashmovers(grid: &mut Grid) {
                 if !can_move(pushed, dir, MoveForce::Push) || is_trash(cell, dir) {
                     return;
                 }
+                grid.emit(UpdateEvent::Trashed { x: x + off.x, y: y + off.y, id: pushed.id() });
+                grid.touch(x + off.x, y + off.y);
             }
             grid.delete(x + off.x, y + off.y);
             push(grid, x, y, dir, 0, None, true);
@@ -569,3 +864,17 @@ fn do_speeds(grid: &mut Grid) {
         }
     });
 }
+
+fn do_players(grid: &mut Grid) {
+    let Some(input) = grid.input.pop_front() else { return };
+    loop_each_dir!(for dir, x, y, cell in grid; {
+        if cell.id() == PLAYER && dir == input && !cell.updated() {
+            cell.set_updated(true);
+            if cell.direction() != dir {
+                cell.set_direction(dir);
+                grid.touch(x, y);
+            }
+            push(grid, x, y, dir, 1, None, false);
+        }
+    });
+}
@@ -2,10 +2,25 @@
 
 mod game;
 
-use game::rendering::WinHandler;
+use std::path::PathBuf;
+
+use game::{rendering::WinHandler, headless::{HeadlessOptions, run_headless}, mods::load_mods};
 use speedy2d::{Window, window::{WindowCreationOptions, WindowSize}, dimen::Vector2};
 
 fn main() {
+    let resource_path = resource_path();
+    load_mods(&resource_path);
+
+    let mut args = std::env::args().skip(1);
+    if args.next().as_deref() == Some("--headless") {
+        let result = HeadlessOptions::parse(args).and_then(|options| run_headless(options, &resource_path));
+        if let Err(err) = result {
+            eprintln!("error: {err}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let window = Window::new_with_options(
         "Quell Machine",
         WindowCreationOptions::new_windowed(
@@ -15,15 +30,19 @@ fn main() {
         )
     ).unwrap();
 
+    window.run_loop(WinHandler::new(resource_path));
+}
+
+/// Folder containing the assets, inside the app bundle on macOS.
+fn resource_path() -> PathBuf {
     let mut path = std::env::current_exe().unwrap();
     path.pop();
     if path.file_name().to_owned().unwrap() == "MacOS" {
         path.pop();
         path.push("Resources");
-        let path = path.iter().filter(|&p| p != ".").collect::<std::path::PathBuf>();
-        window.run_loop(WinHandler::new(path));
+        path.iter().filter(|&p| p != ".").collect::<PathBuf>()
     }
     else {
-        window.run_loop(WinHandler::new(std::env::current_dir().unwrap()));
+        std::env::current_dir().unwrap()
     }
 }